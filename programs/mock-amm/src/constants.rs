@@ -0,0 +1,4 @@
+pub const POOL_TAG: &[u8] = b"pool";
+pub const POOL_VAULT_TAG: &[u8] = b"pool-vault";
+pub const LP_MINT_TAG: &[u8] = b"lp-mint";
+pub const LP_DECIMALS: u8 = 9;
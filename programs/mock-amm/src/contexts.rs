@@ -0,0 +1,93 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_interface::{Mint, TokenAccount, TokenInterface},
+};
+
+use crate::*;
+use constants::*;
+use states::*;
+
+#[derive(Accounts)]
+pub struct CreatePool<'info> {
+    #[account(
+        init,
+        seeds = [POOL_TAG, mint_a.key().as_ref(), mint_b.key().as_ref()],
+        bump,
+        payer = payer,
+        space = std::mem::size_of::<Pool>() + 8
+    )]
+    pub pool: Box<Account<'info, Pool>>,
+
+    pub mint_a: Box<InterfaceAccount<'info, Mint>>,
+    pub mint_b: Box<InterfaceAccount<'info, Mint>>,
+
+    #[account(
+        init,
+        token::token_program = token_program_a,
+        token::mint = mint_a,
+        token::authority = pool,
+        seeds = [POOL_VAULT_TAG, pool.key().as_ref(), mint_a.key().as_ref()],
+        bump,
+        payer = payer,
+    )]
+    pub vault_a: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        init,
+        token::token_program = token_program_b,
+        token::mint = mint_b,
+        token::authority = pool,
+        seeds = [POOL_VAULT_TAG, pool.key().as_ref(), mint_b.key().as_ref()],
+        bump,
+        payer = payer,
+    )]
+    pub vault_b: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        init,
+        mint::token_program = token_program,
+        mint::decimals = LP_DECIMALS,
+        mint::authority = pool,
+        seeds = [LP_MINT_TAG, pool.key().as_ref()],
+        bump,
+        payer = payer,
+    )]
+    pub lp_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub depositor: Signer<'info>,
+
+    #[account(
+        mut,
+        token::token_program = token_program_a,
+        token::mint = mint_a,
+        token::authority = depositor,
+    )]
+    pub depositor_token_a: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        token::token_program = token_program_b,
+        token::mint = mint_b,
+        token::authority = depositor,
+    )]
+    pub depositor_token_b: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        init,
+        associated_token::token_program = token_program,
+        associated_token::mint = lp_mint,
+        associated_token::authority = depositor,
+        payer = payer,
+    )]
+    pub depositor_lp_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    pub token_program_a: Interface<'info, TokenInterface>,
+    pub token_program_b: Interface<'info, TokenInterface>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
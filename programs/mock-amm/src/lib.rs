@@ -0,0 +1,21 @@
+use anchor_lang::prelude::*;
+
+declare_id!("CA2b6NMHdJoqBA6iGja7Y8w9Az3YmiV2WerqgP5ZtAUV");
+
+pub mod constants;
+pub mod contexts;
+pub mod errors;
+mod processors;
+pub mod states;
+pub mod utils;
+
+use contexts::*;
+
+#[program]
+pub mod mock_amm {
+    use super::*;
+
+    pub fn create_pool(ctx: Context<CreatePool>, amount_a: u64, amount_b: u64) -> Result<()> {
+        ctx.accounts.create_pool(amount_a, amount_b, ctx.bumps.pool)
+    }
+}
@@ -0,0 +1,78 @@
+use crate::*;
+
+use anchor_spl::token_interface::{mint_to, transfer_checked, MintTo, TransferChecked};
+use constants::*;
+use errors::*;
+use utils::*;
+
+impl<'info> CreatePool<'info> {
+    pub fn create_pool(&mut self, amount_a: u64, amount_b: u64, pool_bump: u8) -> Result<()> {
+        if amount_a == 0 || amount_b == 0 {
+            return Err(MockAmmError::InvalidAmount.into());
+        }
+
+        let pool = &mut self.pool;
+        pool.mint_a = self.mint_a.key();
+        pool.mint_b = self.mint_b.key();
+        pool.vault_a = self.vault_a.key();
+        pool.vault_b = self.vault_b.key();
+        pool.lp_mint = self.lp_mint.key();
+        pool.reserve_a = amount_a;
+        pool.reserve_b = amount_b;
+
+        transfer_checked(
+            CpiContext::new(
+                self.token_program_a.to_account_info(),
+                TransferChecked {
+                    from: self.depositor_token_a.to_account_info(),
+                    to: self.vault_a.to_account_info(),
+                    mint: self.mint_a.to_account_info(),
+                    authority: self.depositor.to_account_info(),
+                },
+            ),
+            amount_a,
+            self.mint_a.decimals,
+        )?;
+
+        transfer_checked(
+            CpiContext::new(
+                self.token_program_b.to_account_info(),
+                TransferChecked {
+                    from: self.depositor_token_b.to_account_info(),
+                    to: self.vault_b.to_account_info(),
+                    mint: self.mint_b.to_account_info(),
+                    authority: self.depositor.to_account_info(),
+                },
+            ),
+            amount_b,
+            self.mint_b.decimals,
+        )?;
+
+        // initial liquidity is the geometric mean of the deposits
+        let lp_amount = sqrt((amount_a as u128) * (amount_b as u128)) as u64;
+
+        let mint_a_key = self.mint_a.key();
+        let mint_b_key = self.mint_b.key();
+        let signer_seeds: &[&[&[u8]]] = &[&[
+            POOL_TAG,
+            mint_a_key.as_ref(),
+            mint_b_key.as_ref(),
+            &[pool_bump],
+        ]];
+
+        mint_to(
+            CpiContext::new(
+                self.token_program.to_account_info(),
+                MintTo {
+                    mint: self.lp_mint.to_account_info(),
+                    to: self.depositor_lp_token_account.to_account_info(),
+                    authority: self.pool.to_account_info(),
+                },
+            )
+            .with_signer(signer_seeds),
+            lp_amount,
+        )?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1 @@
+pub const PRICE_FEED_TAG: &[u8] = b"price-feed";
@@ -0,0 +1,20 @@
+use anchor_lang::prelude::*;
+
+use crate::*;
+use constants::*;
+
+#[derive(Accounts)]
+pub struct SetPrice<'info> {
+    /// CHECK: created by `set_price` with the Pyth price account layout
+    #[account(
+        mut,
+        seeds = [PRICE_FEED_TAG, authority.key().as_ref()],
+        bump,
+    )]
+    pub price_feed: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
@@ -0,0 +1,26 @@
+use anchor_lang::prelude::*;
+
+declare_id!("E4fTVsUT8N1tStohWgZzwCW5xR39kZu3gY8qxoCaRSzi");
+
+pub mod constants;
+pub mod contexts;
+mod processors;
+pub mod states;
+
+use contexts::*;
+
+#[program]
+pub mod mock_oracle {
+    use super::*;
+
+    pub fn set_price(
+        ctx: Context<SetPrice>,
+        price: i64,
+        conf: u64,
+        exponent: i32,
+        publish_time: i64,
+    ) -> Result<()> {
+        ctx.accounts
+            .set_price(price, conf, exponent, publish_time, ctx.bumps.price_feed)
+    }
+}
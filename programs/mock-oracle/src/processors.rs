@@ -0,0 +1,51 @@
+use crate::*;
+
+use anchor_lang::system_program::{create_account, CreateAccount};
+use constants::*;
+use states::*;
+
+impl<'info> SetPrice<'info> {
+    pub fn set_price(
+        &mut self,
+        price: i64,
+        conf: u64,
+        exponent: i32,
+        publish_time: i64,
+        price_feed_bump: u8,
+    ) -> Result<()> {
+        if self.price_feed.data_is_empty() {
+            let authority = self.authority.key();
+            let signer_seeds: &[&[&[u8]]] =
+                &[&[PRICE_FEED_TAG, authority.as_ref(), &[price_feed_bump]]];
+            create_account(
+                CpiContext::new(
+                    self.system_program.to_account_info(),
+                    CreateAccount {
+                        from: self.authority.to_account_info(),
+                        to: self.price_feed.to_account_info(),
+                    },
+                )
+                .with_signer(signer_seeds),
+                Rent::get()?.minimum_balance(PRICE_ACCOUNT_LEN),
+                PRICE_ACCOUNT_LEN as u64,
+                &crate::ID,
+            )?;
+        }
+
+        let mut data = self.price_feed.try_borrow_mut_data()?;
+        let mut write = |offset: usize, bytes: &[u8]| {
+            data[offset..offset + bytes.len()].copy_from_slice(bytes);
+        };
+        write(MAGIC_OFFSET, &PYTH_MAGIC.to_le_bytes());
+        write(VERSION_OFFSET, &PYTH_VERSION.to_le_bytes());
+        write(ACCOUNT_TYPE_OFFSET, &PYTH_PRICE_ACCOUNT_TYPE.to_le_bytes());
+        write(SIZE_OFFSET, &(PRICE_ACCOUNT_LEN as u32).to_le_bytes());
+        write(EXPONENT_OFFSET, &exponent.to_le_bytes());
+        write(PUBLISH_TIME_OFFSET, &publish_time.to_le_bytes());
+        write(PRICE_OFFSET, &price.to_le_bytes());
+        write(CONF_OFFSET, &conf.to_le_bytes());
+        write(STATUS_OFFSET, &PYTH_STATUS_TRADING.to_le_bytes());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,18 @@
+/// Offsets into a Pyth v2 price account, the fields the presale reads. The
+/// price is `price * 10^exponent` with a confidence interval of `conf` in
+/// the same units.
+pub const PRICE_ACCOUNT_LEN: usize = 240;
+pub const PYTH_MAGIC: u32 = 0xa1b2c3d4;
+pub const PYTH_VERSION: u32 = 2;
+pub const PYTH_PRICE_ACCOUNT_TYPE: u32 = 3;
+pub const PYTH_STATUS_TRADING: u32 = 1;
+
+pub const MAGIC_OFFSET: usize = 0;
+pub const VERSION_OFFSET: usize = 4;
+pub const ACCOUNT_TYPE_OFFSET: usize = 8;
+pub const SIZE_OFFSET: usize = 12;
+pub const EXPONENT_OFFSET: usize = 20;
+pub const PUBLISH_TIME_OFFSET: usize = 96;
+pub const PRICE_OFFSET: usize = 208;
+pub const CONF_OFFSET: usize = 216;
+pub const STATUS_OFFSET: usize = 224;
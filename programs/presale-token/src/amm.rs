@@ -0,0 +1,94 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::{
+    hash::hash,
+    instruction::{AccountMeta, Instruction},
+    program::invoke_signed,
+};
+
+/// Accounts of the AMM's `create_pool` instruction, in instruction order.
+pub struct CreatePool<'info> {
+    pub pool: AccountInfo<'info>,
+    pub mint_a: AccountInfo<'info>,
+    pub mint_b: AccountInfo<'info>,
+    pub vault_a: AccountInfo<'info>,
+    pub vault_b: AccountInfo<'info>,
+    pub lp_mint: AccountInfo<'info>,
+    pub payer: AccountInfo<'info>,
+    pub depositor: AccountInfo<'info>,
+    pub depositor_token_a: AccountInfo<'info>,
+    pub depositor_token_b: AccountInfo<'info>,
+    pub depositor_lp_token_account: AccountInfo<'info>,
+    pub token_program_a: AccountInfo<'info>,
+    pub token_program_b: AccountInfo<'info>,
+    pub token_program: AccountInfo<'info>,
+    pub associated_token_program: AccountInfo<'info>,
+    pub system_program: AccountInfo<'info>,
+}
+
+impl ToAccountMetas for CreatePool<'_> {
+    fn to_account_metas(&self, _is_signer: Option<bool>) -> Vec<AccountMeta> {
+        vec![
+            AccountMeta::new(self.pool.key(), false),
+            AccountMeta::new_readonly(self.mint_a.key(), false),
+            AccountMeta::new_readonly(self.mint_b.key(), false),
+            AccountMeta::new(self.vault_a.key(), false),
+            AccountMeta::new(self.vault_b.key(), false),
+            AccountMeta::new(self.lp_mint.key(), false),
+            AccountMeta::new(self.payer.key(), true),
+            AccountMeta::new_readonly(self.depositor.key(), true),
+            AccountMeta::new(self.depositor_token_a.key(), false),
+            AccountMeta::new(self.depositor_token_b.key(), false),
+            AccountMeta::new(self.depositor_lp_token_account.key(), false),
+            AccountMeta::new_readonly(self.token_program_a.key(), false),
+            AccountMeta::new_readonly(self.token_program_b.key(), false),
+            AccountMeta::new_readonly(self.token_program.key(), false),
+            AccountMeta::new_readonly(self.associated_token_program.key(), false),
+            AccountMeta::new_readonly(self.system_program.key(), false),
+        ]
+    }
+}
+
+impl<'info> ToAccountInfos<'info> for CreatePool<'info> {
+    fn to_account_infos(&self) -> Vec<AccountInfo<'info>> {
+        vec![
+            self.pool.clone(),
+            self.mint_a.clone(),
+            self.mint_b.clone(),
+            self.vault_a.clone(),
+            self.vault_b.clone(),
+            self.lp_mint.clone(),
+            self.payer.clone(),
+            self.depositor.clone(),
+            self.depositor_token_a.clone(),
+            self.depositor_token_b.clone(),
+            self.depositor_lp_token_account.clone(),
+            self.token_program_a.clone(),
+            self.token_program_b.clone(),
+            self.token_program.clone(),
+            self.associated_token_program.clone(),
+            self.system_program.clone(),
+        ]
+    }
+}
+
+/// Creates a constant-product pool seeded with `amount_a` and `amount_b`
+/// through the AMM program configured by the platform admin.
+pub fn create_pool<'info>(
+    ctx: CpiContext<'_, '_, '_, 'info, CreatePool<'info>>,
+    amount_a: u64,
+    amount_b: u64,
+) -> Result<()> {
+    let mut data = hash(b"global:create_pool").to_bytes()[..8].to_vec();
+    amount_a.serialize(&mut data)?;
+    amount_b.serialize(&mut data)?;
+
+    let instruction = Instruction {
+        program_id: ctx.program.key(),
+        accounts: ctx.accounts.to_account_metas(None),
+        data,
+    };
+    let mut account_infos = ctx.accounts.to_account_infos();
+    account_infos.push(ctx.program);
+
+    invoke_signed(&instruction, &account_infos, ctx.signer_seeds).map_err(Into::into)
+}
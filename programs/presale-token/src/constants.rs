@@ -1,10 +1,26 @@
 pub const PRECISION: u64 = 1_000_000_000;
 pub const PERCENT_DENOMINATOR: u64 = 10_000;
-pub const ROUND_COUNT: usize = 4;
+pub const MAX_ROUND_COUNT: usize = 10;
+pub const MAX_PAYMENT_MINT_COUNT: usize = 4;
+pub const MAX_SYMBOL_RULE_COUNT: usize = 8;
+pub const MAX_SYMBOL_RULE_LEN: usize = 10;
+pub const MAX_NAME_LEN: usize = 32;
+pub const MAX_SYMBOL_LEN: usize = 10;
+pub const MAX_URI_LEN: usize = 200;
+
+pub const MERKLE_LEAF_PREFIX: &[u8] = &[0];
+pub const MERKLE_NODE_PREFIX: &[u8] = &[1];
 
 pub const PLATFORM_CONFIG_TAG: &[u8] = b"platform-config";
 pub const PRESALE_CONFIG_TAG: &[u8] = b"presale-config";
+pub const CREATOR_COUNTER_TAG: &[u8] = b"creator-counter";
+pub const NEW_MINT_TAG: &[u8] = b"new-mint";
 pub const VAULT_BASE_TOKEN_ACCOUNT_TAG: &[u8] = b"vault-base-token-account";
+pub const VAULT_SOL_TAG: &[u8] = b"vault-sol";
 pub const USER_ALLOCATION_TAG: &[u8] = b"user-allocation";
 pub const USER_TOKEN_ACCOUNT_TAG: &[u8] = b"user-token-account";
 pub const PLATFORM_FEE_TOKEN_ACCOUNT_TAG: &[u8] = b"platform-fee-token-account";
+pub const LIQUIDITY_TOKEN_ACCOUNT_TAG: &[u8] = b"liquidity-token-account";
+pub const LIQUIDITY_BASE_TOKEN_ACCOUNT_TAG: &[u8] = b"liquidity-base-token-account";
+pub const LIQUIDITY_AUTHORITY_TAG: &[u8] = b"liquidity-authority";
+pub const REFERRER_STATS_TAG: &[u8] = b"referrer-stats";
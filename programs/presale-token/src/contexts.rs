@@ -16,7 +16,7 @@ pub struct Initialize<'info> {
         seeds = [PLATFORM_CONFIG_TAG],
         bump,
         payer = platform_wallet,
-        space = std::mem::size_of::<PlatformConfig>() + 8
+        space = PlatformConfig::space()
     )]
     pub platform_config: Account<'info, PlatformConfig>,
 
@@ -27,7 +27,55 @@ pub struct Initialize<'info> {
 }
 
 #[derive(Accounts)]
-#[instruction(decimals: u8)]
+pub struct UpdatePlatformConfig<'info> {
+    #[account(
+        mut,
+        seeds = [PLATFORM_CONFIG_TAG],
+        bump,
+    )]
+    pub platform_config: Account<'info, PlatformConfig>,
+
+    #[account(
+        constraint = admin.key() == platform_config.admin @ PresaleTokenError::InvalidAdmin
+    )]
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateFeeMint<'info> {
+    #[account(
+        mut,
+        seeds = [PLATFORM_CONFIG_TAG],
+        bump,
+    )]
+    pub platform_config: Account<'info, PlatformConfig>,
+
+    #[account(
+        constraint = admin.key() == platform_config.admin @ PresaleTokenError::InvalidAdmin
+    )]
+    pub admin: Signer<'info>,
+
+    pub fee_mint: InterfaceAccount<'info, Mint>,
+}
+
+#[derive(Accounts)]
+pub struct AcceptAdmin<'info> {
+    #[account(
+        mut,
+        seeds = [PLATFORM_CONFIG_TAG],
+        bump,
+    )]
+    pub platform_config: Account<'info, PlatformConfig>,
+
+    #[account(
+        constraint = pending_admin.key() == platform_config.pending_admin @ PresaleTokenError::InvalidAdmin
+    )]
+    pub pending_admin: Signer<'info>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(params: InitializePresaleParams)]
 pub struct InitializePresale<'info> {
     #[account(
         seeds = [PLATFORM_CONFIG_TAG],
@@ -39,12 +87,21 @@ pub struct InitializePresale<'info> {
     #[account(address = platform_config.platform_wallet)]
     pub platform_wallet: UncheckedAccount<'info>,
 
+    #[account(
+        init_if_needed,
+        seeds = [CREATOR_COUNTER_TAG, creator.key().as_ref()],
+        bump,
+        payer = creator,
+        space = std::mem::size_of::<CreatorCounter>() + 8
+    )]
+    pub creator_counter: Account<'info, CreatorCounter>,
+
     #[account(
         init,
-        seeds = [PRESALE_CONFIG_TAG, creator.key().as_ref()],
+        seeds = [PRESALE_CONFIG_TAG, creator.key().as_ref(), creator_counter.presale_count.to_le_bytes().as_ref()],
         bump,
         payer = creator,
-        space = std::mem::size_of::<PresaleConfig>() + 8
+        space = PresaleConfig::space(params.round_amounts.len())
     )]
     pub presale_config: Account<'info, PresaleConfig>,
 
@@ -53,6 +110,18 @@ pub struct InitializePresale<'info> {
 
     pub base_mint: Box<InterfaceAccount<'info, Mint>>,
 
+    /// Token-2022 mint; its metadata is written in the instruction itself.
+    #[account(
+        init,
+        seeds = [NEW_MINT_TAG, presale_config.key().as_ref()],
+        bump,
+        payer = creator,
+        mint::token_program = token_program_2022,
+        mint::decimals = params.decimals,
+        mint::authority = presale_config,
+        extensions::metadata_pointer::authority = presale_config,
+        extensions::metadata_pointer::metadata_address = new_mint,
+    )]
     pub new_mint: Box<InterfaceAccount<'info, Mint>>,
 
     #[account(address = platform_config.fee_mint)]
@@ -69,7 +138,7 @@ pub struct InitializePresale<'info> {
         init_if_needed,
         token::mint = fee_mint,
         token::authority = platform_wallet,
-        seeds = [PLATFORM_FEE_TOKEN_ACCOUNT_TAG],
+        seeds = [PLATFORM_FEE_TOKEN_ACCOUNT_TAG, platform_wallet.key().as_ref(), fee_mint.key().as_ref()],
         bump,
         payer = creator,
     )]
@@ -85,15 +154,62 @@ pub struct InitializePresale<'info> {
     )]
     pub vault_base_token_account: InterfaceAccount<'info, TokenAccount>,
 
+    #[account(
+        mut,
+        seeds = [VAULT_SOL_TAG, presale_config.key().as_ref()],
+        bump,
+    )]
+    pub vault_sol: SystemAccount<'info>,
+
     pub token_program: Interface<'info, TokenInterface>,
+    pub token_program_2022: Interface<'info, TokenInterface>,
     pub system_program: Program<'info, System>,
 }
 
+#[event_cpi]
+#[derive(Accounts)]
+pub struct AddPaymentMint<'info> {
+    #[account(
+        mut,
+        seeds = [PRESALE_CONFIG_TAG, presale_config.creator.as_ref(), presale_config.presale_id.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub presale_config: Account<'info, PresaleConfig>,
+
+    #[account(
+        mut,
+        constraint = creator.key() == presale_config.creator @ PresaleTokenError::InvalidCreator
+    )]
+    pub creator: Signer<'info>,
+
+    pub payment_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        init,
+        token::mint = payment_mint,
+        token::authority = presale_config,
+        seeds = [VAULT_BASE_TOKEN_ACCOUNT_TAG, presale_config.key().as_ref(), payment_mint.key().as_ref()],
+        bump,
+        payer = creator,
+    )]
+    pub vault_payment_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
 #[derive(Accounts)]
 pub struct BuySellPretoken<'info> {
+    #[account(
+        seeds = [PLATFORM_CONFIG_TAG],
+        bump,
+    )]
+    pub platform_config: Account<'info, PlatformConfig>,
+
     #[account(
         mut,
-        seeds = [PRESALE_CONFIG_TAG, presale_config.creator.as_ref()],
+        seeds = [PRESALE_CONFIG_TAG, presale_config.creator.as_ref(), presale_config.presale_id.to_le_bytes().as_ref()],
         bump,
     )]
     pub presale_config: Account<'info, PresaleConfig>,
@@ -105,12 +221,12 @@ pub struct BuySellPretoken<'info> {
         seeds = [USER_ALLOCATION_TAG, presale_config.key().as_ref(), user.key().as_ref()],
         bump,
         payer = user,
-        space = std::mem::size_of::<UserAllocation>() + 8
+        space = UserAllocation::space(presale_config.round_count())
     )]
     pub user_allocation: Account<'info, UserAllocation>,
 
     #[account(
-        constraint = base_mint.key() == presale_config.base_mint @ PresaleTokenError::InvalidBaseMint
+        constraint = presale_config.accepts_mint(&base_mint.key()) @ PresaleTokenError::InvalidBaseMint
     )]
     pub base_mint: InterfaceAccount<'info, Mint>,
 
@@ -118,6 +234,7 @@ pub struct BuySellPretoken<'info> {
         mut,
         token::mint = base_mint,
         token::authority = presale_config,
+        constraint = vault_base_token_account.key() == presale_config.vault_address(&presale_config.key(), &base_mint.key()) @ PresaleTokenError::InvalidVault,
     )]
     pub vault_base_token_account: InterfaceAccount<'info, TokenAccount>,
 
@@ -126,17 +243,62 @@ pub struct BuySellPretoken<'info> {
         token::mint = base_mint,
         token::authority = user,
     )]
-    pub user_base_token_account: InterfaceAccount<'info, TokenAccount>,
+    pub user_base_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        seeds = [VAULT_SOL_TAG, presale_config.key().as_ref()],
+        bump = presale_config.vault_sol_bump,
+    )]
+    pub vault_sol: SystemAccount<'info>,
+
+    #[account(
+        mut,
+        constraint = referrer_stats.presale == presale_config.key() @ PresaleTokenError::InvalidReferrer
+    )]
+    pub referrer_stats: Option<Account<'info, ReferrerStats>>,
+
+    /// CHECK: checked against the presale's oracle config and read by `oracle`
+    pub price_feed: Option<UncheckedAccount<'info>>,
 
     pub token_program: Interface<'info, TokenInterface>,
     pub system_program: Program<'info, System>,
 }
 
+#[derive(Accounts)]
+pub struct RegisterReferrer<'info> {
+    #[account(
+        seeds = [PRESALE_CONFIG_TAG, presale_config.creator.as_ref(), presale_config.presale_id.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub presale_config: Account<'info, PresaleConfig>,
+    #[account(mut)]
+    pub referrer: Signer<'info>,
+
+    #[account(
+        init,
+        seeds = [REFERRER_STATS_TAG, presale_config.key().as_ref(), referrer.key().as_ref()],
+        bump,
+        payer = referrer,
+        space = std::mem::size_of::<ReferrerStats>() + 8
+    )]
+    pub referrer_stats: Account<'info, ReferrerStats>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
 #[derive(Accounts)]
 pub struct FinalizePresale<'info> {
+    #[account(
+        seeds = [PLATFORM_CONFIG_TAG],
+        bump,
+    )]
+    pub platform_config: Account<'info, PlatformConfig>,
+
     #[account(
         mut,
-        seeds = [PRESALE_CONFIG_TAG, presale_config.creator.as_ref()],
+        seeds = [PRESALE_CONFIG_TAG, presale_config.creator.as_ref(), presale_config.presale_id.to_le_bytes().as_ref()],
         bump,
     )]
     pub presale_config: Account<'info, PresaleConfig>,
@@ -147,30 +309,129 @@ pub struct FinalizePresale<'info> {
     )]
     pub creator: Signer<'info>,
 
-    pub new_mint: InterfaceAccount<'info, Mint>,
+    #[account(
+        mut,
+        constraint = new_mint.key() == presale_config.new_mint @ PresaleTokenError::InvalidNewMint
+    )]
+    pub new_mint: Box<InterfaceAccount<'info, Mint>>,
 
     #[account(
         constraint = base_mint.key() == presale_config.base_mint @ PresaleTokenError::InvalidBaseMint
     )]
-    pub base_mint: InterfaceAccount<'info, Mint>,
+    pub base_mint: Box<InterfaceAccount<'info, Mint>>,
 
     #[account(
         mut,
         token::mint = base_mint,
         token::authority = presale_config,
+        seeds = [VAULT_BASE_TOKEN_ACCOUNT_TAG, presale_config.key().as_ref()],
+        bump,
     )]
-    pub vault_base_token_account: InterfaceAccount<'info, TokenAccount>,
+    pub vault_base_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
 
+    #[account(
+        mut,
+        seeds = [VAULT_SOL_TAG, presale_config.key().as_ref()],
+        bump = presale_config.vault_sol_bump,
+    )]
+    pub vault_sol: SystemAccount<'info>,
+
+    /// signs the pool deposit in place of the presale, so the AMM only ever
+    /// sees a signature over the two liquidity token accounts
+    #[account(
+        seeds = [LIQUIDITY_AUTHORITY_TAG, presale_config.key().as_ref()],
+        bump,
+    )]
+    pub liquidity_authority: SystemAccount<'info>,
+
+    #[account(
+        init_if_needed,
+        token::token_program = token_program_2022,
+        token::mint = new_mint,
+        token::authority = liquidity_authority,
+        seeds = [LIQUIDITY_TOKEN_ACCOUNT_TAG, presale_config.key().as_ref()],
+        bump,
+        payer = creator,
+    )]
+    pub liquidity_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        init_if_needed,
+        token::token_program = token_program,
+        token::mint = base_mint,
+        token::authority = liquidity_authority,
+        seeds = [LIQUIDITY_BASE_TOKEN_ACCOUNT_TAG, presale_config.key().as_ref()],
+        bump,
+        payer = creator,
+    )]
+    pub liquidity_base_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// receives the unsold supply when it is returned to the creator
+    #[account(
+        mut,
+        token::mint = new_mint,
+        token::authority = creator,
+    )]
+    pub creator_token_account: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    /// CHECK: initialized and validated by the amm program
+    #[account(mut)]
+    pub amm_pool: UncheckedAccount<'info>,
+
+    /// CHECK: initialized and validated by the amm program
+    #[account(mut)]
+    pub amm_vault_new_token_account: UncheckedAccount<'info>,
+
+    /// CHECK: initialized and validated by the amm program
+    #[account(mut)]
+    pub amm_vault_base_token_account: UncheckedAccount<'info>,
+
+    /// CHECK: initialized and validated by the amm program
+    #[account(mut)]
+    pub amm_lp_mint: UncheckedAccount<'info>,
+
+    /// CHECK: initialized and validated by the amm program
+    #[account(mut)]
+    pub lp_token_account: UncheckedAccount<'info>,
+
+    /// CHECK: the AMM configured by the platform admin
+    #[account(
+        executable,
+        address = platform_config.amm_program @ PresaleTokenError::InvalidAmmProgram
+    )]
+    pub amm_program: UncheckedAccount<'info>,
     pub token_program_2022: Interface<'info, TokenInterface>,
     pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
     pub system_program: Program<'info, System>,
 }
 
+#[event_cpi]
+#[derive(Accounts)]
+pub struct CancelPresale<'info> {
+    #[account(
+        seeds = [PLATFORM_CONFIG_TAG],
+        bump,
+    )]
+    pub platform_config: Account<'info, PlatformConfig>,
+
+    #[account(
+        mut,
+        seeds = [PRESALE_CONFIG_TAG, presale_config.creator.as_ref(), presale_config.presale_id.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub presale_config: Account<'info, PresaleConfig>,
+
+    /// the creator before the presale starts, or the platform admin
+    pub authority: Signer<'info>,
+}
+
+#[event_cpi]
 #[derive(Accounts)]
 pub struct ClaimOrRefund<'info> {
     #[account(
         mut,
-        seeds = [PRESALE_CONFIG_TAG, presale_config.creator.as_ref()],
+        seeds = [PRESALE_CONFIG_TAG, presale_config.creator.as_ref(), presale_config.presale_id.to_le_bytes().as_ref()],
         bump,
     )]
     pub presale_config: Account<'info, PresaleConfig>,
@@ -202,7 +463,7 @@ pub struct ClaimOrRefund<'info> {
     pub user_token_account: InterfaceAccount<'info, TokenAccount>,
 
     #[account(
-        constraint = base_mint.key() == presale_config.base_mint @ PresaleTokenError::InvalidBaseMint
+        constraint = presale_config.accepts_mint(&base_mint.key()) @ PresaleTokenError::InvalidBaseMint
     )]
     pub base_mint: InterfaceAccount<'info, Mint>,
 
@@ -210,6 +471,7 @@ pub struct ClaimOrRefund<'info> {
         mut,
         token::mint = base_mint,
         token::authority = presale_config,
+        constraint = vault_base_token_account.key() == presale_config.vault_address(&presale_config.key(), &base_mint.key()) @ PresaleTokenError::InvalidVault,
     )]
     pub vault_base_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
 
@@ -218,9 +480,248 @@ pub struct ClaimOrRefund<'info> {
         token::mint = base_mint,
         token::authority = user,
     )]
-    pub user_base_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+    pub user_base_token_account: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    #[account(
+        mut,
+        seeds = [VAULT_SOL_TAG, presale_config.key().as_ref()],
+        bump = presale_config.vault_sol_bump,
+    )]
+    pub vault_sol: SystemAccount<'info>,
 
     pub token_program: Interface<'info, TokenInterface>,
     pub token_program_2022: Interface<'info, TokenInterface>,
     pub system_program: Program<'info, System>,
 }
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct WithdrawProceeds<'info> {
+    #[account(
+        seeds = [PLATFORM_CONFIG_TAG],
+        bump,
+    )]
+    pub platform_config: Account<'info, PlatformConfig>,
+
+    /// CHECK: platform wallet, receives the commission of native SOL presales
+    #[account(mut, address = platform_config.platform_wallet)]
+    pub platform_wallet: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [PRESALE_CONFIG_TAG, presale_config.creator.as_ref(), presale_config.presale_id.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub presale_config: Account<'info, PresaleConfig>,
+
+    #[account(
+        mut,
+        constraint = creator.key() == presale_config.creator @ PresaleTokenError::InvalidCreator
+    )]
+    pub creator: Signer<'info>,
+
+    #[account(
+        constraint = presale_config.accepts_mint(&base_mint.key()) @ PresaleTokenError::InvalidBaseMint
+    )]
+    pub base_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    #[account(
+        mut,
+        token::mint = base_mint,
+        token::authority = presale_config,
+        constraint = vault_base_token_account.key() == presale_config.vault_address(&presale_config.key(), &base_mint.key()) @ PresaleTokenError::InvalidVault,
+    )]
+    pub vault_base_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        token::mint = base_mint,
+        token::authority = creator,
+    )]
+    pub creator_base_token_account: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    #[account(
+        mut,
+        token::mint = base_mint,
+        token::authority = platform_wallet,
+    )]
+    pub platform_fee_token_account: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    #[account(
+        mut,
+        seeds = [VAULT_SOL_TAG, presale_config.key().as_ref()],
+        bump = presale_config.vault_sol_bump,
+    )]
+    pub vault_sol: SystemAccount<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct ClaimReferralRewards<'info> {
+    #[account(
+        seeds = [PRESALE_CONFIG_TAG, presale_config.creator.as_ref(), presale_config.presale_id.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub presale_config: Account<'info, PresaleConfig>,
+    #[account(mut)]
+    pub referrer: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [REFERRER_STATS_TAG, presale_config.key().as_ref(), referrer.key().as_ref()],
+        bump,
+    )]
+    pub referrer_stats: Account<'info, ReferrerStats>,
+
+    #[account(
+        mut,
+        constraint = new_mint.key() == presale_config.new_mint @ PresaleTokenError::InvalidNewMint
+    )]
+    pub new_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        init_if_needed,
+        token::token_program = token_program_2022,
+        token::mint = new_mint,
+        token::authority = referrer,
+        seeds = [USER_TOKEN_ACCOUNT_TAG, presale_config.key().as_ref(), referrer.key().as_ref()],
+        bump,
+        payer = referrer,
+    )]
+    pub referrer_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        constraint = base_mint.key() == presale_config.base_mint @ PresaleTokenError::InvalidBaseMint
+    )]
+    pub base_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mut,
+        token::mint = base_mint,
+        token::authority = presale_config,
+        seeds = [VAULT_BASE_TOKEN_ACCOUNT_TAG, presale_config.key().as_ref()],
+        bump,
+    )]
+    pub vault_base_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        token::mint = base_mint,
+        token::authority = referrer,
+    )]
+    pub referrer_base_token_account: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    #[account(
+        mut,
+        seeds = [VAULT_SOL_TAG, presale_config.key().as_ref()],
+        bump = presale_config.vault_sol_bump,
+    )]
+    pub vault_sol: SystemAccount<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub token_program_2022: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CloseUserAllocation<'info> {
+    #[account(
+        mut,
+        seeds = [PRESALE_CONFIG_TAG, presale_config.creator.as_ref(), presale_config.presale_id.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub presale_config: Account<'info, PresaleConfig>,
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [USER_ALLOCATION_TAG, presale_config.key().as_ref(), user.key().as_ref()],
+        bump,
+        close = user,
+    )]
+    pub user_allocation: Account<'info, UserAllocation>,
+
+    /// closed along with the allocation when passed, it must be empty
+    #[account(
+        mut,
+        token::token_program = token_program_2022,
+        token::authority = user,
+        seeds = [USER_TOKEN_ACCOUNT_TAG, presale_config.key().as_ref(), user.key().as_ref()],
+        bump,
+    )]
+    pub user_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    pub token_program_2022: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct CloseReferrerStats<'info> {
+    #[account(
+        mut,
+        seeds = [PRESALE_CONFIG_TAG, presale_config.creator.as_ref(), presale_config.presale_id.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub presale_config: Account<'info, PresaleConfig>,
+    #[account(mut)]
+    pub referrer: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [REFERRER_STATS_TAG, presale_config.key().as_ref(), referrer.key().as_ref()],
+        bump,
+        close = referrer,
+    )]
+    pub referrer_stats: Account<'info, ReferrerStats>,
+}
+
+#[derive(Accounts)]
+pub struct ClosePresale<'info> {
+    #[account(
+        mut,
+        seeds = [PRESALE_CONFIG_TAG, presale_config.creator.as_ref(), presale_config.presale_id.to_le_bytes().as_ref()],
+        bump,
+        close = creator,
+    )]
+    pub presale_config: Account<'info, PresaleConfig>,
+
+    #[account(
+        mut,
+        constraint = creator.key() == presale_config.creator @ PresaleTokenError::InvalidCreator
+    )]
+    pub creator: Signer<'info>,
+
+    #[account(
+        constraint = base_mint.key() == presale_config.base_mint @ PresaleTokenError::InvalidBaseMint
+    )]
+    pub base_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    #[account(
+        mut,
+        token::mint = base_mint,
+        token::authority = presale_config,
+        seeds = [VAULT_BASE_TOKEN_ACCOUNT_TAG, presale_config.key().as_ref()],
+        bump,
+    )]
+    pub vault_base_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        token::mint = base_mint,
+        token::authority = creator,
+    )]
+    pub creator_base_token_account: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    #[account(
+        mut,
+        seeds = [VAULT_SOL_TAG, presale_config.key().as_ref()],
+        bump = presale_config.vault_sol_bump,
+    )]
+    pub vault_sol: SystemAccount<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
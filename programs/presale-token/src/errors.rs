@@ -46,4 +46,142 @@ pub enum PresaleTokenError {
 
     #[msg("Invalid new mint")]
     InvalidNewMint,
+
+    #[msg("Invalid liquidity percent")]
+    InvalidLiquidityPercent,
+
+    #[msg("Presale not succeeded")]
+    PresaleNotSucceeded,
+
+    #[msg("Insufficient proceeds")]
+    InsufficientProceeds,
+
+    #[msg("Invalid fee percent")]
+    InvalidFeePercent,
+
+    #[msg("Math overflow")]
+    MathOverflow,
+
+    #[msg("Math underflow")]
+    MathUnderflow,
+
+    #[msg("Invalid supply")]
+    InvalidSupply,
+
+    #[msg("Invalid round count")]
+    InvalidRoundCount,
+
+    #[msg("Invalid round schedule")]
+    InvalidRoundSchedule,
+
+    #[msg("Invalid purchase limits")]
+    InvalidPurchaseLimits,
+
+    #[msg("Purchase below minimum")]
+    BelowMinPurchase,
+
+    #[msg("Purchase exceeds maximum per wallet")]
+    ExceedsMaxPerWallet,
+
+    #[msg("Purchase exceeds maximum per transaction")]
+    ExceedsMaxPerTransaction,
+
+    #[msg("Wallet not allowlisted")]
+    NotAllowlisted,
+
+    #[msg("Purchase exceeds allowlist cap")]
+    ExceedsAllowlistCap,
+
+    #[msg("Invalid vesting schedule")]
+    InvalidVestingSchedule,
+
+    #[msg("Nothing to claim")]
+    NothingToClaim,
+
+    #[msg("Invalid referral config")]
+    InvalidReferralConfig,
+
+    #[msg("Invalid referrer")]
+    InvalidReferrer,
+
+    #[msg("Self referral not allowed")]
+    SelfReferral,
+
+    #[msg("Base token account required")]
+    MissingBaseTokenAccount,
+
+    #[msg("Invalid payment rate")]
+    InvalidPaymentRate,
+
+    #[msg("Too many payment mints")]
+    TooManyPaymentMints,
+
+    #[msg("Payment mint already added")]
+    DuplicatePaymentMint,
+
+    #[msg("Insufficient amount paid with this mint")]
+    InsufficientPaymentAmount,
+
+    #[msg("Invalid oracle config")]
+    InvalidOracleConfig,
+
+    #[msg("Invalid price feed")]
+    InvalidPriceFeed,
+
+    #[msg("Invalid oracle price")]
+    InvalidOraclePrice,
+
+    #[msg("Oracle price is stale")]
+    StalePrice,
+
+    #[msg("Oracle price confidence too wide")]
+    PriceConfidenceTooWide,
+
+    #[msg("Invalid pricing mode")]
+    InvalidPricingMode,
+
+    #[msg("Not supported in pro-rata mode")]
+    ProRataNotSupported,
+
+    #[msg("Invalid soft or hard cap")]
+    InvalidCap,
+
+    #[msg("Hard cap reached")]
+    HardCapReached,
+
+    #[msg("Creator token account required")]
+    MissingCreatorTokenAccount,
+
+    #[msg("Presale already started")]
+    PresaleAlreadyStarted,
+
+    #[msg("Presale cancelled")]
+    PresaleCancelled,
+
+    #[msg("Allocation not settled")]
+    AllocationNotSettled,
+
+    #[msg("Referral rewards not claimed")]
+    RewardsNotClaimed,
+
+    #[msg("Presale not settled")]
+    PresaleNotSettled,
+
+    #[msg("Invalid payment vault")]
+    InvalidPaymentVault,
+
+    #[msg("Invalid special symbol rules")]
+    InvalidSymbolRules,
+
+    #[msg("Invalid token metadata")]
+    InvalidTokenMetadata,
+
+    #[msg("Invalid AMM program")]
+    InvalidAmmProgram,
+
+    #[msg("Invalid vault")]
+    InvalidVault,
+
+    #[msg("Not enough supply left for referral rewards")]
+    InsufficientReferralReserve,
 }
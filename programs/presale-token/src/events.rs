@@ -1 +1,129 @@
+use anchor_lang::prelude::*;
 
+#[event]
+pub struct PresaleInitialized {
+    pub presale: Pubkey,
+    pub creator: Pubkey,
+    pub presale_id: u64,
+    pub base_mint: Pubkey,
+    pub new_mint: Pubkey,
+    pub start_timestamp: u64,
+    pub duration: u64,
+    pub soft_cap_amount: u64,
+    pub hard_cap_amount: u64,
+    pub liquidity_amount: u64,
+    pub fee_percent: u16,
+    pub platform_fee_amount: u64,
+}
+
+#[event]
+pub struct PaymentMintAdded {
+    pub presale: Pubkey,
+    pub mint: Pubkey,
+    pub rate: u64,
+}
+
+#[event]
+pub struct PretokenBought {
+    pub presale: Pubkey,
+    pub user: Pubkey,
+    pub round: u8,
+    pub pretoken_amount: u64,
+    pub base_amount: u64,
+    pub payment_mint: Pubkey,
+    pub payment_amount: u64,
+    pub remained_amount: u64,
+    pub total_buy_amount: u64,
+    pub user_amount: u64,
+    pub user_base_amount: u64,
+}
+
+#[event]
+pub struct PretokenSold {
+    pub presale: Pubkey,
+    pub user: Pubkey,
+    pub round: u8,
+    pub pretoken_amount: u64,
+    pub base_amount: u64,
+    pub fee_amount: u64,
+    pub payment_mint: Pubkey,
+    pub payment_amount: u64,
+    pub remained_amount: u64,
+    pub total_buy_amount: u64,
+    pub total_return_amount: u64,
+    pub user_amount: u64,
+    pub user_base_amount: u64,
+}
+
+#[event]
+pub struct RoundAdvanced {
+    pub presale: Pubkey,
+    pub previous_round: u8,
+    pub current_round: u8,
+}
+
+#[event]
+pub struct PresaleFinalized {
+    pub presale: Pubkey,
+    pub is_success: bool,
+    pub total_buy_amount: u64,
+    pub unsold_amount: u64,
+    pub liquidity_amount: u64,
+    pub liquidity_base_amount: u64,
+    pub proceeds_amount: u64,
+}
+
+#[event]
+pub struct PresaleCancelled {
+    pub presale: Pubkey,
+    pub authority: Pubkey,
+    pub total_buy_amount: u64,
+}
+
+#[event]
+pub struct ProceedsWithdrawn {
+    pub presale: Pubkey,
+    pub creator: Pubkey,
+    pub payment_mint: Pubkey,
+    pub amount: u64,
+    pub fee_amount: u64,
+    pub withdrawn_amount: u64,
+}
+
+#[event]
+pub struct Claimed {
+    pub presale: Pubkey,
+    pub user: Pubkey,
+    pub pretoken_amount: u64,
+    pub claimed_amount: u64,
+    pub total_amount: u64,
+    pub excess_base_amount: u64,
+}
+
+#[event]
+pub struct Refunded {
+    pub presale: Pubkey,
+    pub user: Pubkey,
+    pub base_amount: u64,
+    pub fee_amount: u64,
+    pub payment_mint: Pubkey,
+    pub payment_amount: u64,
+}
+
+#[event]
+pub struct ReferralRewarded {
+    pub presale: Pubkey,
+    pub user: Pubkey,
+    pub referrer: Pubkey,
+    pub pretoken_amount: u64,
+    pub base_reward_amount: u64,
+    pub pretoken_reward_amount: u64,
+}
+
+#[event]
+pub struct ReferralRewardsClaimed {
+    pub presale: Pubkey,
+    pub referrer: Pubkey,
+    pub base_amount: u64,
+    pub pretoken_amount: u64,
+}
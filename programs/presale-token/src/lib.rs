@@ -2,73 +2,210 @@ use anchor_lang::prelude::*;
 
 declare_id!("3Nf68MDDEkjCLv3xGeedZkFVfRTC2Nz1viNHB19taXig");
 
+pub mod amm;
 pub mod constants;
 pub mod contexts;
 pub mod errors;
 pub mod events;
+pub mod merkle;
+pub mod oracle;
 mod processors;
 pub mod states;
 pub mod utils;
 
-use constants::*;
 use contexts::*;
-use utils::*;
+use events::*;
+use states::*;
 
 #[program]
 pub mod presale_token {
-    use constants::ROUND_COUNT;
-
     use super::*;
 
     pub fn initialize(ctx: Context<Initialize>) -> Result<()> {
         ctx.accounts.initialize()
     }
 
+    pub fn update_platform_wallet(
+        ctx: Context<UpdatePlatformConfig>,
+        platform_wallet: Pubkey,
+    ) -> Result<()> {
+        ctx.accounts.update_platform_wallet(platform_wallet)
+    }
+
+    pub fn update_fee_amounts(
+        ctx: Context<UpdatePlatformConfig>,
+        fee_percent: u16,
+        fee_amount_normal: u64,
+        fee_amount_special: u64,
+    ) -> Result<()> {
+        ctx.accounts
+            .update_fee_amounts(fee_percent, fee_amount_normal, fee_amount_special)
+    }
+
+    pub fn update_special_symbol_rules(
+        ctx: Context<UpdatePlatformConfig>,
+        rules: Vec<SymbolRule>,
+    ) -> Result<()> {
+        ctx.accounts.update_special_symbol_rules(rules)
+    }
+
+    pub fn update_oracle_program(
+        ctx: Context<UpdatePlatformConfig>,
+        oracle_program: Pubkey,
+    ) -> Result<()> {
+        ctx.accounts.update_oracle_program(oracle_program)
+    }
+
+    pub fn update_amm_program(
+        ctx: Context<UpdatePlatformConfig>,
+        amm_program: Pubkey,
+    ) -> Result<()> {
+        ctx.accounts.update_amm_program(amm_program)
+    }
+
+    pub fn update_fee_mint(ctx: Context<UpdateFeeMint>) -> Result<()> {
+        ctx.accounts.update_fee_mint()
+    }
+
+    pub fn propose_admin(ctx: Context<UpdatePlatformConfig>, new_admin: Pubkey) -> Result<()> {
+        ctx.accounts.propose_admin(new_admin)
+    }
+
+    pub fn accept_admin(ctx: Context<AcceptAdmin>) -> Result<()> {
+        ctx.accounts.accept_admin()
+    }
+
     pub fn initialize_presale(
         ctx: Context<InitializePresale>,
-        decimals: u8,
-        start_timestamp: u64,
-        duration: u64,
-        round_prices: [u64; ROUND_COUNT],
-        fee_percent: u16,
+        params: InitializePresaleParams,
+    ) -> Result<()> {
+        let event = ctx.accounts.initialize_presale(
+            params,
+            ctx.bumps.presale_config,
+            ctx.bumps.vault_sol,
+        )?;
+        emit_cpi!(event);
+
+        Ok(())
+    }
+
+    pub fn add_payment_mint(ctx: Context<AddPaymentMint>, rate: u64) -> Result<()> {
+        let event = ctx.accounts.add_payment_mint(rate)?;
+        emit_cpi!(event);
+
+        Ok(())
+    }
+
+    pub fn buy_pretoken(
+        ctx: Context<BuySellPretoken>,
+        round: u8,
+        amount: u64,
+        allowlist_proof: Option<AllowlistProof>,
     ) -> Result<()> {
-        let multiplier = 10_u64.pow(decimals as u32);
-        let total_supply = 1_000_000_000 as u64 * multiplier; // 1B token
-        let round_amounts: [u64; 4] = [
-            245_000_000 as u64 * multiplier,
-            235_000_000 as u64 * multiplier,
-            215_000_000 as u64 * multiplier,
-            105_000_000 as u64 * multiplier,
-        ];
-        let target_amount =
-            round_amounts[0] + round_amounts[1] + round_amounts[2] + round_amounts[3];
-        let liquidity_amount = total_supply - target_amount;
-        ctx.accounts.initialize_presale(
-            decimals,
-            start_timestamp,
-            duration,
-            round_amounts,
-            round_prices,
-            liquidity_amount,
-            target_amount,
-            fee_percent,
-        )
-    }
-
-    pub fn buy_pretoken(ctx: Context<BuySellPretoken>, round: u8, amount: u64) -> Result<()> {
-        ctx.accounts.buy_pretoken(round, amount)
+        let previous_round = ctx.accounts.presale_config.current_round;
+        let event = ctx.accounts.buy_pretoken(round, amount, allowlist_proof)?;
+        let referral_event = ctx
+            .accounts
+            .reward_referrer(event.pretoken_amount, event.base_amount)?;
+        emit_cpi!(event);
+        if let Some(referral_event) = referral_event {
+            emit_cpi!(referral_event);
+        }
+
+        let current_round = ctx.accounts.presale_config.current_round;
+        if current_round != previous_round {
+            emit_cpi!(RoundAdvanced {
+                presale: ctx.accounts.presale_config.key(),
+                previous_round,
+                current_round,
+            });
+        }
+
+        Ok(())
     }
 
     pub fn sell_pretoken(ctx: Context<BuySellPretoken>, round: u8, amount: u64) -> Result<()> {
-        ctx.accounts
-            .sell_pretoken(round, amount, ctx.bumps.presale_config)
+        let previous_round = ctx.accounts.presale_config.current_round;
+        let event = ctx
+            .accounts
+            .sell_pretoken(round, amount, ctx.bumps.presale_config)?;
+        ctx.accounts.revoke_referral_reward(event.pretoken_amount)?;
+        emit_cpi!(event);
+
+        let current_round = ctx.accounts.presale_config.current_round;
+        if current_round != previous_round {
+            emit_cpi!(RoundAdvanced {
+                presale: ctx.accounts.presale_config.key(),
+                previous_round,
+                current_round,
+            });
+        }
+
+        Ok(())
+    }
+
+    pub fn register_referrer(ctx: Context<RegisterReferrer>) -> Result<()> {
+        ctx.accounts.register_referrer()
     }
 
     pub fn finalize_presale(ctx: Context<FinalizePresale>) -> Result<()> {
-        ctx.accounts.finalize_presale()
+        let event = ctx
+            .accounts
+            .finalize_presale(ctx.bumps.presale_config, ctx.bumps.liquidity_authority)?;
+        emit_cpi!(event);
+
+        Ok(())
+    }
+
+    pub fn cancel_presale(ctx: Context<CancelPresale>) -> Result<()> {
+        let event = ctx.accounts.cancel_presale()?;
+        emit_cpi!(event);
+
+        Ok(())
+    }
+
+    pub fn withdraw_proceeds(ctx: Context<WithdrawProceeds>, amount: u64) -> Result<()> {
+        let event = ctx
+            .accounts
+            .withdraw_proceeds(amount, ctx.bumps.presale_config)?;
+        emit_cpi!(event);
+
+        Ok(())
     }
 
     pub fn claim_or_refund(ctx: Context<ClaimOrRefund>) -> Result<()> {
-        ctx.accounts.claim_or_refund(ctx.bumps.presale_config)
+        if ctx.accounts.presale_config.is_success {
+            let event = ctx.accounts.claim(ctx.bumps.presale_config)?;
+            emit_cpi!(event);
+        } else {
+            let event = ctx.accounts.refund(ctx.bumps.presale_config)?;
+            emit_cpi!(event);
+        }
+
+        Ok(())
+    }
+
+    pub fn claim_referral_rewards(ctx: Context<ClaimReferralRewards>) -> Result<()> {
+        let event = ctx
+            .accounts
+            .claim_referral_rewards(ctx.bumps.presale_config)?;
+        emit_cpi!(event);
+
+        Ok(())
+    }
+
+    pub fn close_user_allocation(ctx: Context<CloseUserAllocation>) -> Result<()> {
+        ctx.accounts.close_user_allocation()
+    }
+
+    pub fn close_referrer_stats(ctx: Context<CloseReferrerStats>) -> Result<()> {
+        ctx.accounts.close_referrer_stats()
+    }
+
+    pub fn close_presale<'info>(
+        ctx: Context<'_, '_, '_, 'info, ClosePresale<'info>>,
+    ) -> Result<()> {
+        ctx.accounts
+            .close_presale(ctx.remaining_accounts, ctx.bumps.presale_config)
     }
 }
@@ -0,0 +1,68 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::keccak::hashv;
+
+use crate::*;
+use constants::*;
+
+/// Leaf for an allowlisted wallet; a zero `cap` means the wallet is uncapped.
+pub fn leaf_hash(wallet: &Pubkey, cap: u64) -> [u8; 32] {
+    hashv(&[MERKLE_LEAF_PREFIX, wallet.as_ref(), &cap.to_le_bytes()]).to_bytes()
+}
+
+/// Pairs are hashed in sorted order so proofs don't need left/right flags.
+pub fn node_hash(a: &[u8; 32], b: &[u8; 32]) -> [u8; 32] {
+    let (left, right) = if a <= b { (a, b) } else { (b, a) };
+    hashv(&[MERKLE_NODE_PREFIX, left, right]).to_bytes()
+}
+
+pub fn verify_proof(root: &[u8; 32], leaf: [u8; 32], proof: &[[u8; 32]]) -> bool {
+    proof
+        .iter()
+        .fold(leaf, |hash, sibling| node_hash(&hash, sibling))
+        == *root
+}
+
+/// Off-chain tree builder producing roots and proofs that `verify_proof` accepts.
+/// A node without a sibling is carried up to the next layer unchanged. Only
+/// built with the `merkle-tree` feature, which the allowlist tool enables.
+#[cfg(feature = "merkle-tree")]
+pub struct MerkleTree {
+    layers: Vec<Vec<[u8; 32]>>,
+}
+
+#[cfg(feature = "merkle-tree")]
+impl MerkleTree {
+    pub fn new(leaves: Vec<[u8; 32]>) -> Self {
+        assert!(!leaves.is_empty(), "merkle tree needs at least one leaf");
+
+        let mut layers = vec![leaves];
+        while layers[layers.len() - 1].len() > 1 {
+            let next = layers[layers.len() - 1]
+                .chunks(2)
+                .map(|pair| match pair {
+                    [left, right] => node_hash(left, right),
+                    [single] => *single,
+                    _ => unreachable!(),
+                })
+                .collect();
+            layers.push(next);
+        }
+
+        Self { layers }
+    }
+
+    pub fn root(&self) -> [u8; 32] {
+        self.layers[self.layers.len() - 1][0]
+    }
+
+    pub fn proof(&self, mut index: usize) -> Vec<[u8; 32]> {
+        let mut proof = Vec::new();
+        for layer in &self.layers[..self.layers.len() - 1] {
+            if let Some(sibling) = layer.get(index ^ 1) {
+                proof.push(*sibling);
+            }
+            index /= 2;
+        }
+        proof
+    }
+}
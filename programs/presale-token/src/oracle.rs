@@ -0,0 +1,70 @@
+use anchor_lang::prelude::*;
+
+use crate::*;
+use errors::*;
+
+/// A `base_mint`/USD price: `price * 10^exponent` with a confidence
+/// interval of `conf` in the same units.
+pub struct OraclePrice {
+    pub price: i64,
+    pub conf: u64,
+    pub exponent: i32,
+    pub publish_time: i64,
+}
+
+/// Reads the latest price out of a price feed account's data.
+pub trait PriceFeedReader {
+    fn read_price(data: &[u8]) -> Result<OraclePrice>;
+}
+
+/// Pyth v2 price accounts, read at their aggregate price.
+pub struct PythPriceFeed;
+
+impl PythPriceFeed {
+    const MAGIC: u32 = 0xa1b2c3d4;
+    const VERSION: u32 = 2;
+    const PRICE_ACCOUNT_TYPE: u32 = 3;
+    const STATUS_TRADING: u32 = 1;
+    const LEN: usize = 240;
+
+    fn read<const N: usize>(data: &[u8], offset: usize) -> [u8; N] {
+        data[offset..offset + N].try_into().unwrap()
+    }
+}
+
+impl PriceFeedReader for PythPriceFeed {
+    fn read_price(data: &[u8]) -> Result<OraclePrice> {
+        if data.len() < Self::LEN
+            || u32::from_le_bytes(Self::read(data, 0)) != Self::MAGIC
+            || u32::from_le_bytes(Self::read(data, 4)) != Self::VERSION
+            || u32::from_le_bytes(Self::read(data, 8)) != Self::PRICE_ACCOUNT_TYPE
+        {
+            return Err(PresaleTokenError::InvalidPriceFeed.into());
+        }
+        // a halted or unknown status leaves a price that is not current
+        if u32::from_le_bytes(Self::read(data, 224)) != Self::STATUS_TRADING {
+            return Err(PresaleTokenError::InvalidOraclePrice.into());
+        }
+
+        Ok(OraclePrice {
+            price: i64::from_le_bytes(Self::read(data, 208)),
+            conf: u64::from_le_bytes(Self::read(data, 216)),
+            exponent: i32::from_le_bytes(Self::read(data, 20)),
+            publish_time: i64::from_le_bytes(Self::read(data, 96)),
+        })
+    }
+}
+
+/// Reads `price_feed` once it is known to be `expected_feed` and owned by
+/// the oracle program configured by the platform admin.
+pub fn load_price<R: PriceFeedReader>(
+    price_feed: Option<&AccountInfo>,
+    expected_feed: &Pubkey,
+    oracle_program: &Pubkey,
+) -> Result<OraclePrice> {
+    let price_feed = price_feed
+        .filter(|price_feed| price_feed.key == expected_feed && price_feed.owner == oracle_program)
+        .ok_or(PresaleTokenError::InvalidPriceFeed)?;
+
+    R::read_price(&price_feed.try_borrow_data()?)
+}
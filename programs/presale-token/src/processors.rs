@@ -1,60 +1,344 @@
 use crate::*;
 
-use anchor_spl::token_2022::{mint_to, transfer_checked, MintTo, TransferChecked};
+use anchor_lang::system_program::{transfer, Transfer};
+use anchor_spl::token::spl_token::native_mint;
+use anchor_spl::token_2022::{
+    close_account, mint_to, sync_native, transfer_checked, CloseAccount, MintTo, SyncNative,
+    TransferChecked,
+};
+use anchor_spl::token_2022_extensions::{
+    spl_pod::optional_keys::OptionalNonZeroPubkey,
+    spl_token_metadata_interface::state::TokenMetadata, token_metadata_initialize,
+    TokenMetadataInitialize,
+};
+use anchor_spl::token_interface::{Mint, TokenAccount};
 use constants::*;
 use errors::*;
 use events::*;
+use merkle::*;
 use states::*;
+use utils::*;
+
+/// Lamports in the SOL vault on top of its rent-exempt reserve.
+fn vault_sol_amount(vault_sol: &AccountInfo) -> Result<u64> {
+    checked_sub(vault_sol.lamports(), Rent::get()?.minimum_balance(0))
+}
+
+fn transfer_from_vault_sol<'info>(
+    vault_sol: AccountInfo<'info>,
+    to: AccountInfo<'info>,
+    system_program: AccountInfo<'info>,
+    presale: Pubkey,
+    vault_sol_bump: u8,
+    amount: u64,
+) -> Result<()> {
+    let signer_seeds: &[&[&[u8]]] = &[&[VAULT_SOL_TAG, presale.as_ref(), &[vault_sol_bump]]];
+
+    transfer(
+        CpiContext::new(
+            system_program,
+            Transfer {
+                from: vault_sol,
+                to,
+            },
+        )
+        .with_signer(signer_seeds),
+        amount,
+    )
+}
 
 impl<'info> Initialize<'info> {
     pub fn initialize(&mut self) -> Result<()> {
         let platform_config = &mut self.platform_config;
+        platform_config.admin = self.platform_wallet.key();
+        platform_config.pending_admin = Pubkey::default();
         platform_config.platform_wallet = self.platform_wallet.key();
         platform_config.fee_mint = self.fee_mint.key();
         platform_config.fee_amount_normal = 2_000_000;
         platform_config.fee_amount_special = 10_000_000;
+        platform_config.special_symbol_rules = vec![SymbolRule {
+            kind: SymbolMatch::Suffix,
+            pattern: "safe".to_string(),
+        }];
 
         Ok(())
     }
 }
 
-impl<'info> InitializePresale<'info> {
-    pub fn initialize_presale(
+impl<'info> UpdatePlatformConfig<'info> {
+    pub fn update_platform_wallet(&mut self, platform_wallet: Pubkey) -> Result<()> {
+        self.platform_config.platform_wallet = platform_wallet;
+
+        Ok(())
+    }
+
+    pub fn update_fee_amounts(
         &mut self,
-        decimals: u8,
-        start_timestamp: u64,
-        duration: u64,
-        round_amounts: [u64; ROUND_COUNT],
-        round_prices: [u64; ROUND_COUNT],
-        liquidity_amount: u64,
-        target_amount: u64,
         fee_percent: u16,
+        fee_amount_normal: u64,
+        fee_amount_special: u64,
     ) -> Result<()> {
+        if fee_percent as u64 > PERCENT_DENOMINATOR {
+            return Err(PresaleTokenError::InvalidFeePercent.into());
+        }
+
+        let platform_config = &mut self.platform_config;
+        platform_config.fee_percent = fee_percent;
+        platform_config.fee_amount_normal = fee_amount_normal;
+        platform_config.fee_amount_special = fee_amount_special;
+
+        Ok(())
+    }
+
+    pub fn update_special_symbol_rules(&mut self, rules: Vec<SymbolRule>) -> Result<()> {
+        if rules.len() > MAX_SYMBOL_RULE_COUNT {
+            return Err(PresaleTokenError::InvalidSymbolRules.into());
+        }
+        for rule in rules.iter() {
+            rule.validate()?;
+        }
+
+        self.platform_config.special_symbol_rules = rules;
+
+        Ok(())
+    }
+
+    pub fn update_oracle_program(&mut self, oracle_program: Pubkey) -> Result<()> {
+        self.platform_config.oracle_program = oracle_program;
+
+        Ok(())
+    }
+
+    pub fn update_amm_program(&mut self, amm_program: Pubkey) -> Result<()> {
+        self.platform_config.amm_program = amm_program;
+
+        Ok(())
+    }
+
+    /// The proposed admin only takes over once it signs `accept_admin`,
+    /// so a mistyped key can be replaced by proposing again.
+    pub fn propose_admin(&mut self, new_admin: Pubkey) -> Result<()> {
+        self.platform_config.pending_admin = new_admin;
+
+        Ok(())
+    }
+}
+
+impl<'info> UpdateFeeMint<'info> {
+    pub fn update_fee_mint(&mut self) -> Result<()> {
+        self.platform_config.fee_mint = self.fee_mint.key();
+
+        Ok(())
+    }
+}
+
+impl<'info> AcceptAdmin<'info> {
+    pub fn accept_admin(&mut self) -> Result<()> {
+        let platform_config = &mut self.platform_config;
+        platform_config.admin = self.pending_admin.key();
+        platform_config.pending_admin = Pubkey::default();
+
+        Ok(())
+    }
+}
+
+impl<'info> InitializePresale<'info> {
+    pub fn initialize_presale(
+        &mut self,
+        params: InitializePresaleParams,
+        presale_config_bump: u8,
+        vault_sol_bump: u8,
+    ) -> Result<PresaleInitialized> {
+        if params.name.len() > MAX_NAME_LEN
+            || params.symbol.is_empty()
+            || params.symbol.len() > MAX_SYMBOL_LEN
+            || params.uri.len() > MAX_URI_LEN
+        {
+            return Err(PresaleTokenError::InvalidTokenMetadata.into());
+        }
+        if params.liquidity_percent as u64 > PERCENT_DENOMINATOR {
+            return Err(PresaleTokenError::InvalidLiquidityPercent.into());
+        }
+        let round_count = params.round_amounts.len();
+        if round_count == 0
+            || round_count > MAX_ROUND_COUNT
+            || params.round_prices.len() != round_count
+            || params.round_end_times.len() != round_count
+        {
+            return Err(PresaleTokenError::InvalidRoundCount.into());
+        }
+
+        // each round starts when the previous one ends, the first at start_timestamp
+        let presale_end_timestamp = checked_add(params.start_timestamp, params.duration)?;
+        let mut round_start_time = params.start_timestamp;
+        for round_end_time in params.round_end_times.iter() {
+            if *round_end_time <= round_start_time {
+                return Err(PresaleTokenError::InvalidRoundSchedule.into());
+            }
+            round_start_time = *round_end_time;
+        }
+        if round_start_time > presale_end_timestamp {
+            return Err(PresaleTokenError::InvalidRoundSchedule.into());
+        }
+
+        if (!params.round_purchase_limits.is_empty()
+            && params.round_purchase_limits.len() != round_count)
+            || (!params.round_merkle_roots.is_empty()
+                && params.round_merkle_roots.len() != round_count)
+            || (!params.round_vestings.is_empty() && params.round_vestings.len() != round_count)
+            || (!params.round_pricing_modes.is_empty()
+                && params.round_pricing_modes.len() != round_count)
+        {
+            return Err(PresaleTokenError::InvalidRoundCount.into());
+        }
+        params.purchase_limits.validate()?;
+        for limits in params.round_purchase_limits.iter() {
+            limits.validate()?;
+        }
+        params.vesting.validate()?;
+        for vesting in params.round_vestings.iter().flatten() {
+            vesting.validate()?;
+        }
+        params.referral.validate()?;
+        for (pricing_mode, price) in params
+            .round_pricing_modes
+            .iter()
+            .zip(params.round_prices.iter())
+        {
+            if let PricingMode::Linear { end_price } = pricing_mode {
+                if end_price < price {
+                    return Err(PresaleTokenError::InvalidPricingMode.into());
+                }
+            }
+        }
+        if let Some(oracle) = params.oracle {
+            oracle.validate()?;
+        }
+        // commitments are refunded at the round's average price and rewards
+        // would be paid on pretokens that are never allocated
+        if params.is_pro_rata
+            && (params.referral.reward_percent != 0
+                || params
+                    .round_pricing_modes
+                    .iter()
+                    .any(|pricing_mode| *pricing_mode != PricingMode::Fixed))
+        {
+            return Err(PresaleTokenError::ProRataNotSupported.into());
+        }
+        if params.round_amounts.contains(&0) {
+            return Err(PresaleTokenError::InvalidAmount.into());
+        }
+
+        let allocated_supply = params
+            .round_amounts
+            .iter()
+            .try_fold(params.liquidity_amount, |sum, amount| {
+                checked_add(sum, *amount)
+            })?;
+        if allocated_supply != params.total_supply {
+            return Err(PresaleTokenError::InvalidSupply.into());
+        }
+        let round_supply = checked_sub(params.total_supply, params.liquidity_amount)?;
+        if params.soft_cap == 0
+            || params.soft_cap > params.hard_cap
+            || params.hard_cap > round_supply
+        {
+            return Err(PresaleTokenError::InvalidCap.into());
+        }
+        // pro-rata rounds are filled on their own, so the cap can't bind earlier
+        if params.is_pro_rata && params.hard_cap != round_supply {
+            return Err(PresaleTokenError::ProRataNotSupported.into());
+        }
+
+        // scaling the total supply also proves every part of it fits in u64
+        let multiplier = checked_pow10(params.decimals)?;
+        checked_mul(params.total_supply, multiplier)?;
+        let liquidity_amount = checked_mul(params.liquidity_amount, multiplier)?;
+        let soft_cap_amount = checked_mul(params.soft_cap, multiplier)?;
+        let hard_cap_amount = checked_mul(params.hard_cap, multiplier)?;
+
+        let creator_counter = &mut self.creator_counter;
+        let presale_id = creator_counter.presale_count;
+        creator_counter.presale_count = checked_add(presale_id, 1)?;
+
         let presale_config = &mut self.presale_config;
         presale_config.creator = self.creator.key();
+        presale_config.presale_id = presale_id;
         presale_config.base_mint = self.base_mint.key();
+        presale_config.base_decimals = self.base_mint.decimals;
+        presale_config.is_native = self.base_mint.key() == native_mint::ID;
+        presale_config.vault_sol_bump = vault_sol_bump;
         presale_config.new_mint = self.new_mint.key();
-        presale_config.decimals = decimals;
+        presale_config.decimals = params.decimals;
         presale_config.current_round = 0;
-        presale_config.start_timestamp = start_timestamp;
-        presale_config.duration = duration;
+        presale_config.purchase_limits = params.purchase_limits;
+        presale_config.vesting = params.vesting;
+        presale_config.referral = params.referral;
+        presale_config.oracle = params.oracle;
+        presale_config.is_pro_rata = params.is_pro_rata;
+        presale_config.soft_cap_amount = soft_cap_amount;
+        presale_config.hard_cap_amount = hard_cap_amount;
+        presale_config.unsold_action = params.unsold_action;
+        presale_config.unsold_amount = 0;
+        presale_config.sellout_cooldown = params.sellout_cooldown;
+        presale_config.sellout_timestamp = 0;
+        presale_config.start_timestamp = params.start_timestamp;
+        presale_config.duration = params.duration;
         presale_config.liquidity_amount = liquidity_amount;
-        presale_config.target_amount = target_amount;
+        presale_config.liquidity_percent = params.liquidity_percent;
+        presale_config.liquidity_base_amount = 0;
         presale_config.total_return_amount = 0;
-        presale_config.fee_percent = fee_percent;
+        presale_config.fee_percent = params.fee_percent;
         presale_config.is_finalized = false;
         presale_config.is_success = false;
 
-        for i in 0..ROUND_COUNT {
-            presale_config.round_configs[i] = RoundConfig {
-                round_end_time: 0,
-                round_total_amount: round_amounts[i],
-                remained_amount: round_amounts[i],
-                price: round_prices[i],
-            };
+        presale_config.round_configs = Vec::with_capacity(round_count);
+        for i in 0..round_count {
+            let round_amount = checked_mul(params.round_amounts[i], multiplier)?;
+            presale_config.round_configs.push(RoundConfig {
+                round_end_time: params.round_end_times[i],
+                round_total_amount: round_amount,
+                remained_amount: round_amount,
+                price: params.round_prices[i],
+                pricing_mode: params
+                    .round_pricing_modes
+                    .get(i)
+                    .copied()
+                    .unwrap_or_default(),
+                purchase_limits: params
+                    .round_purchase_limits
+                    .get(i)
+                    .copied()
+                    .unwrap_or_default(),
+                merkle_root: params.round_merkle_roots.get(i).copied().flatten(),
+                vesting: params.round_vestings.get(i).copied().flatten(),
+                committed_amount: 0,
+                committed_base_amount: 0,
+            });
+        }
+
+        // keep the SOL vault rent-exempt so it can be drained to any amount
+        if presale_config.is_native {
+            let reserve_amount = Rent::get()?
+                .minimum_balance(0)
+                .saturating_sub(self.vault_sol.lamports());
+            if reserve_amount > 0 {
+                transfer(
+                    CpiContext::new(
+                        self.system_program.to_account_info(),
+                        Transfer {
+                            from: self.creator.to_account_info(),
+                            to: self.vault_sol.to_account_info(),
+                        },
+                    ),
+                    reserve_amount,
+                )?;
+            }
         }
 
-        let is_special = false; // TODO: need to check if symbol name ends with "safe"
+        self.initialize_metadata(&params, presale_id, presale_config_bump)?;
+
+        let is_special = self.platform_config.is_special_symbol(&params.symbol);
         let fee_amount = if is_special {
             self.platform_config.fee_amount_special
         } else {
@@ -76,35 +360,182 @@ impl<'info> InitializePresale<'info> {
             self.fee_mint.decimals,
         )?;
 
-        Ok(())
+        Ok(PresaleInitialized {
+            presale: self.presale_config.key(),
+            creator: self.creator.key(),
+            presale_id,
+            base_mint: self.base_mint.key(),
+            new_mint: self.new_mint.key(),
+            start_timestamp: params.start_timestamp,
+            duration: params.duration,
+            soft_cap_amount,
+            hard_cap_amount,
+            liquidity_amount,
+            fee_percent: params.fee_percent,
+            platform_fee_amount: fee_amount,
+        })
+    }
+
+    /// Writes the name, symbol and uri into `new_mint`, topping up its rent
+    /// for the grown account first. The presale config keeps the update
+    /// authority, so the creator cannot rename the symbol the platform fee
+    /// was charged on.
+    fn initialize_metadata(
+        &self,
+        params: &InitializePresaleParams,
+        presale_id: u64,
+        presale_config_bump: u8,
+    ) -> Result<()> {
+        let metadata = TokenMetadata {
+            update_authority: OptionalNonZeroPubkey(self.presale_config.key()),
+            mint: self.new_mint.key(),
+            name: params.name.clone(),
+            symbol: params.symbol.clone(),
+            uri: params.uri.clone(),
+            additional_metadata: vec![],
+        };
+        let new_mint = self.new_mint.to_account_info();
+        let rent_amount = Rent::get()?
+            .minimum_balance(new_mint.data_len() + metadata.tlv_size_of()?)
+            .saturating_sub(new_mint.lamports());
+        if rent_amount > 0 {
+            transfer(
+                CpiContext::new(
+                    self.system_program.to_account_info(),
+                    Transfer {
+                        from: self.creator.to_account_info(),
+                        to: new_mint.clone(),
+                    },
+                ),
+                rent_amount,
+            )?;
+        }
+
+        let creator = self.creator.key();
+        let presale_id = presale_id.to_le_bytes();
+        let signer_seeds: &[&[&[u8]]] = &[&[
+            PRESALE_CONFIG_TAG,
+            creator.as_ref(),
+            &presale_id,
+            &[presale_config_bump],
+        ]];
+
+        token_metadata_initialize(
+            CpiContext::new(
+                self.token_program_2022.to_account_info(),
+                TokenMetadataInitialize {
+                    token_program_id: self.token_program_2022.to_account_info(),
+                    metadata: new_mint.clone(),
+                    update_authority: self.presale_config.to_account_info(),
+                    mint_authority: self.presale_config.to_account_info(),
+                    mint: new_mint,
+                },
+            )
+            .with_signer(signer_seeds),
+            params.name.clone(),
+            params.symbol.clone(),
+            params.uri.clone(),
+        )
+    }
+}
+
+impl<'info> AddPaymentMint<'info> {
+    /// Rates are fixed before the presale starts, so buyers never see the
+    /// creator reprice a mint mid-sale.
+    pub fn add_payment_mint(&mut self, rate: u64) -> Result<PaymentMintAdded> {
+        let presale_config = &mut self.presale_config;
+        let mint = self.payment_mint.key();
+
+        if presale_config.is_finalized {
+            return Err(PresaleTokenError::PresaleAlreadyFinalized.into());
+        }
+        let clock = Clock::get()?;
+        if clock.unix_timestamp as u64 >= presale_config.start_timestamp {
+            return Err(PresaleTokenError::PresaleAlreadyStarted.into());
+        }
+        if rate == 0 {
+            return Err(PresaleTokenError::InvalidPaymentRate.into());
+        }
+        if presale_config.is_pro_rata {
+            return Err(PresaleTokenError::ProRataNotSupported.into());
+        }
+        if presale_config.accepts_mint(&mint) {
+            return Err(PresaleTokenError::DuplicatePaymentMint.into());
+        }
+        if presale_config.payment_mints.len() >= MAX_PAYMENT_MINT_COUNT {
+            return Err(PresaleTokenError::TooManyPaymentMints.into());
+        }
+
+        presale_config.payment_mints.push(PaymentMint {
+            mint,
+            rate,
+            vault_amount: 0,
+            withdrawn_amount: 0,
+        });
+
+        Ok(PaymentMintAdded {
+            presale: presale_config.key(),
+            mint,
+            rate,
+        })
     }
 }
 
 impl<'info> BuySellPretoken<'info> {
-    pub fn buy_pretoken(&mut self, round: u8, mut amount: u64) -> Result<()> {
-        if round >= ROUND_COUNT as u8 {
+    pub fn buy_pretoken(
+        &mut self,
+        round: u8,
+        mut amount: u64,
+        allowlist_proof: Option<AllowlistProof>,
+    ) -> Result<PretokenBought> {
+        let presale_config = &mut self.presale_config;
+        if round as usize >= presale_config.round_count() {
             return Err(PresaleTokenError::InvalidRound.into());
         }
+        if presale_config.is_cancelled {
+            return Err(PresaleTokenError::PresaleCancelled.into());
+        }
+        if presale_config.is_finalized {
+            return Err(PresaleTokenError::PresaleAlreadyFinalized.into());
+        }
 
-        let presale_config = &mut self.presale_config;
+        let clock = Clock::get()?;
+        let current_timestamp = clock.unix_timestamp as u64;
+        presale_config.advance_round(current_timestamp);
 
         let round_config = presale_config.round_configs[round as usize];
         if amount == 0 {
             return Err(PresaleTokenError::InvalidAmount.into());
         }
 
-        let amount_limit = if round < ROUND_COUNT as u8 - 1 {
-            round_config.remained_amount
+        // pro-rata commitments can go past the round amount and the hard cap,
+        // which equals the round supply, and are scaled down at finalization
+        let amount_limit = if presale_config.is_pro_rata {
+            u64::MAX
         } else {
-            round_config.remained_amount + presale_config.total_return_amount
+            let round_limit = if round < presale_config.last_round() {
+                round_config.remained_amount
+            } else {
+                checked_add(
+                    round_config.remained_amount,
+                    presale_config.total_return_amount,
+                )?
+            };
+            std::cmp::min(
+                round_limit,
+                checked_sub(
+                    presale_config.hard_cap_amount,
+                    presale_config.total_buy_amount,
+                )?,
+            )
         };
+        if amount_limit == 0 {
+            return Err(PresaleTokenError::HardCapReached.into());
+        }
         if amount > amount_limit {
             amount = amount_limit;
         }
 
-        let clock = Clock::get()?;
-        let current_timestamp = clock.unix_timestamp as u64;
-
         if round > presale_config.current_round {
             return Err(PresaleTokenError::RoundNotStarted.into());
         }
@@ -117,63 +548,187 @@ impl<'info> BuySellPretoken<'info> {
             return Err(PresaleTokenError::PresaleNotStarted.into());
         }
 
-        let _amount_for_returned: u64 = if round == ROUND_COUNT as u8 - 1 {
+        let user_allocation = &self.user_allocation;
+        if let Some(merkle_root) = round_config.merkle_root {
+            let allowlist_proof = allowlist_proof.ok_or(PresaleTokenError::NotAllowlisted)?;
+            let leaf = leaf_hash(&self.user.key(), allowlist_proof.cap);
+            if !verify_proof(&merkle_root, leaf, &allowlist_proof.proof) {
+                return Err(PresaleTokenError::NotAllowlisted.into());
+            }
+            if allowlist_proof.cap != 0
+                && checked_add(user_allocation.round_amount(round), amount)? > allowlist_proof.cap
+            {
+                return Err(PresaleTokenError::ExceedsAllowlistCap.into());
+            }
+        }
+
+        presale_config.purchase_limits.check(
+            amount,
+            amount_limit,
+            user_allocation.total_amount()?,
+        )?;
+        round_config.purchase_limits.check(
+            amount,
+            amount_limit,
+            user_allocation.round_amount(round),
+        )?;
+
+        let _amount_for_returned: u64 = if round == presale_config.last_round() {
             std::cmp::min(amount, presale_config.total_return_amount)
         } else {
             0
         };
-        let mut _amount_for_round: u64 = amount - _amount_for_returned;
+        let _amount_for_round: u64 = checked_sub(amount, _amount_for_returned)?;
 
-        let new_remained_amount = round_config.remained_amount - _amount_for_round;
+        let new_remained_amount = if presale_config.is_pro_rata {
+            // commitments past the round amount are scaled down at finalization
+            round_config
+                .remained_amount
+                .saturating_sub(_amount_for_round)
+        } else {
+            checked_sub(round_config.remained_amount, _amount_for_round)?
+        };
 
         presale_config.round_configs[round as usize].remained_amount = new_remained_amount;
-        presale_config.total_return_amount -= _amount_for_returned;
-        presale_config.total_buy_amount = presale_config.total_buy_amount + amount;
+        presale_config.total_return_amount =
+            checked_sub(presale_config.total_return_amount, _amount_for_returned)?;
+        presale_config.total_buy_amount = checked_add(presale_config.total_buy_amount, amount)?;
 
-        if new_remained_amount == 0 {
-            presale_config.current_round = presale_config.current_round + 1;
+        if new_remained_amount == 0 && !presale_config.is_pro_rata {
+            presale_config.current_round = presale_config
+                .current_round
+                .checked_add(1)
+                .ok_or(PresaleTokenError::MathOverflow)?;
+        }
+        // pro-rata presales keep taking commitments until they end
+        if presale_config.total_buy_amount == presale_config.hard_cap_amount
+            && !presale_config.is_pro_rata
+            && presale_config.sellout_timestamp == 0
+        {
+            presale_config.sellout_timestamp = current_timestamp;
         }
 
-        let base_amount = (((amount as u128) * (round_config.price as u128) + PRECISION as u128
-            - 1)
-            / PRECISION as u128) as u64;
+        let start_price = presale_config.base_price(
+            round_config.price,
+            self.price_feed.as_deref(),
+            &self.platform_config.oracle_program,
+            current_timestamp,
+            true,
+        )?;
+        let end_price = presale_config.base_price(
+            round_config.end_price(),
+            self.price_feed.as_deref(),
+            &self.platform_config.oracle_program,
+            current_timestamp,
+            true,
+        )?;
+        let base_amount = round_config.buy_cost(start_price, end_price, amount)?;
+        if presale_config.is_pro_rata {
+            let committed_round = &mut presale_config.round_configs[round as usize];
+            committed_round.committed_amount =
+                checked_add(committed_round.committed_amount, amount)?;
+            committed_round.committed_base_amount =
+                checked_add(committed_round.committed_base_amount, base_amount)?;
+        }
+        let payment_index = presale_config.payment_mint_index(&self.base_mint.key())?;
+        let payment_amount = match payment_index {
+            Some(index) => {
+                let payment_mint = &mut presale_config.payment_mints[index];
+                let payment_amount = base_to_payment_ceil(base_amount, payment_mint.rate)?;
+                payment_mint.vault_amount = checked_add(payment_mint.vault_amount, payment_amount)?;
+                payment_amount
+            }
+            None => base_amount,
+        };
 
         // transfer base amount
-        transfer_checked(
-            CpiContext::new(
-                self.token_program.to_account_info(),
-                TransferChecked {
-                    from: self.user_base_token_account.to_account_info(),
-                    to: self.vault_base_token_account.to_account_info(),
-                    mint: self.base_mint.to_account_info(),
-                    authority: self.user.to_account_info(),
-                },
-            ),
-            base_amount,
-            self.base_mint.decimals,
-        )?;
+        if payment_index.is_none() && presale_config.is_native {
+            transfer(
+                CpiContext::new(
+                    self.system_program.to_account_info(),
+                    Transfer {
+                        from: self.user.to_account_info(),
+                        to: self.vault_sol.to_account_info(),
+                    },
+                ),
+                payment_amount,
+            )?;
+        } else {
+            let user_base_token_account = self
+                .user_base_token_account
+                .as_ref()
+                .ok_or(PresaleTokenError::MissingBaseTokenAccount)?;
+            transfer_checked(
+                CpiContext::new(
+                    self.token_program.to_account_info(),
+                    TransferChecked {
+                        from: user_base_token_account.to_account_info(),
+                        to: self.vault_base_token_account.to_account_info(),
+                        mint: self.base_mint.to_account_info(),
+                        authority: self.user.to_account_info(),
+                    },
+                ),
+                payment_amount,
+                self.base_mint.decimals,
+            )?;
+        }
 
         // update allocation
         let user_allocation = &mut self.user_allocation;
-        user_allocation.amounts[round as usize] = user_allocation.amounts[round as usize] + amount;
-        // user_allocation.base_amount = user_allocation.base_amount + base_amount;
+        if user_allocation.amounts.is_empty() {
+            user_allocation.amounts = vec![0; self.presale_config.round_count()];
+            self.presale_config.participant_count =
+                checked_add(self.presale_config.participant_count, 1)?;
+        }
+        user_allocation.amounts[round as usize] =
+            checked_add(user_allocation.amounts[round as usize], amount)?;
+        user_allocation.add_paid_amount(payment_index, base_amount)?;
 
-        Ok(())
+        Ok(PretokenBought {
+            presale: self.presale_config.key(),
+            user: self.user.key(),
+            round,
+            pretoken_amount: amount,
+            base_amount,
+            payment_mint: self.base_mint.key(),
+            payment_amount,
+            remained_amount: new_remained_amount,
+            total_buy_amount: self.presale_config.total_buy_amount,
+            user_amount: user_allocation.total_amount()?,
+            user_base_amount: user_allocation.base_amount,
+        })
     }
 
-    pub fn sell_pretoken(&mut self, round: u8, amount: u64, presale_config_bump: u8) -> Result<()> {
-        if round >= ROUND_COUNT as u8 {
+    pub fn sell_pretoken(
+        &mut self,
+        round: u8,
+        amount: u64,
+        presale_config_bump: u8,
+    ) -> Result<PretokenSold> {
+        let presale_config = &mut self.presale_config;
+        let user_allocation = &mut self.user_allocation;
+        if round as usize >= presale_config.round_count() {
             return Err(PresaleTokenError::InvalidRound.into());
         }
+        if presale_config.is_cancelled {
+            return Err(PresaleTokenError::PresaleCancelled.into());
+        }
+        if presale_config.is_finalized {
+            return Err(PresaleTokenError::PresaleAlreadyFinalized.into());
+        }
+        if presale_config.is_pro_rata {
+            return Err(PresaleTokenError::ProRataNotSupported.into());
+        }
 
-        let presale_config = &mut self.presale_config;
-        let user_allocation = &mut self.user_allocation;
+        let clock = Clock::get()?;
+        let current_timestamp = clock.unix_timestamp as u64;
+        presale_config.advance_round(current_timestamp);
 
         let round_config = presale_config.round_configs[round as usize];
         if amount == 0 {
             return Err(PresaleTokenError::InvalidAmount.into());
         }
-        if amount > user_allocation.amounts[round as usize] {
+        if amount > user_allocation.round_amount(round) {
             return Err(PresaleTokenError::InvalidAmount.into());
         }
 
@@ -181,55 +736,280 @@ impl<'info> BuySellPretoken<'info> {
             return Err(PresaleTokenError::RoundNotStarted.into());
         }
 
-        if round == presale_config.current_round && round != ROUND_COUNT as u8 - 1 {
+        if round == presale_config.current_round && round != presale_config.last_round() {
             return Err(PresaleTokenError::NotFinalRound.into());
         }
 
-        let new_remained_amount = round_config.remained_amount + amount;
+        let new_remained_amount = checked_add(round_config.remained_amount, amount)?;
 
         presale_config.round_configs[round as usize].remained_amount = new_remained_amount;
-        presale_config.total_buy_amount = presale_config.total_buy_amount - amount;
+        presale_config.total_buy_amount = checked_sub(presale_config.total_buy_amount, amount)?;
+        presale_config.sellout_timestamp = 0;
 
-        let base_amount =
-            ((amount as u128 * round_config.price as u128) / (PRECISION as u128)) as u64;
-        let fee_amount = (base_amount as u128 * presale_config.fee_percent as u128
-            / PERCENT_DENOMINATOR as u128) as u64;
-        let return_base_amount = base_amount - fee_amount;
+        let start_price = presale_config.base_price(
+            round_config.price,
+            self.price_feed.as_deref(),
+            &self.platform_config.oracle_program,
+            current_timestamp,
+            false,
+        )?;
+        let end_price = presale_config.base_price(
+            round_config.end_price(),
+            self.price_feed.as_deref(),
+            &self.platform_config.oracle_program,
+            current_timestamp,
+            false,
+        )?;
+        let base_amount = round_config.sell_value(start_price, end_price, amount)?;
+        let fee_amount = percent_of(base_amount, presale_config.fee_percent)?;
+        let return_base_amount = checked_sub(base_amount, fee_amount)?;
+
+        // paid out with the same mint the pretokens were bought with
+        let payment_index = presale_config.payment_mint_index(&self.base_mint.key())?;
+        user_allocation.sub_paid_amount(payment_index, base_amount)?;
+        let payment_amount = match payment_index {
+            Some(index) => {
+                let payment_mint = &mut presale_config.payment_mints[index];
+                let payment_amount = base_to_payment_floor(return_base_amount, payment_mint.rate)?;
+                payment_mint.vault_amount = checked_sub(payment_mint.vault_amount, payment_amount)?;
+                payment_amount
+            }
+            None => return_base_amount,
+        };
 
+        let presale_id = presale_config.presale_id.to_le_bytes();
         let signer_seeds: &[&[&[u8]]] = &[&[
             PRESALE_CONFIG_TAG,
             presale_config.creator.as_ref(),
+            &presale_id,
             &[presale_config_bump],
         ]];
 
         // transfer base amount
-        transfer_checked(
-            CpiContext::new(
-                self.token_program.to_account_info(),
-                TransferChecked {
-                    from: self.vault_base_token_account.to_account_info(),
-                    to: self.user_base_token_account.to_account_info(),
-                    mint: self.base_mint.to_account_info(),
-                    authority: self.user.to_account_info(),
-                },
-            )
-            .with_signer(signer_seeds),
-            return_base_amount,
-            self.base_mint.decimals,
-        )?;
+        if payment_index.is_none() && presale_config.is_native {
+            transfer_from_vault_sol(
+                self.vault_sol.to_account_info(),
+                self.user.to_account_info(),
+                self.system_program.to_account_info(),
+                presale_config.key(),
+                presale_config.vault_sol_bump,
+                payment_amount,
+            )?;
+        } else {
+            let user_base_token_account = self
+                .user_base_token_account
+                .as_ref()
+                .ok_or(PresaleTokenError::MissingBaseTokenAccount)?;
+            transfer_checked(
+                CpiContext::new(
+                    self.token_program.to_account_info(),
+                    TransferChecked {
+                        from: self.vault_base_token_account.to_account_info(),
+                        to: user_base_token_account.to_account_info(),
+                        mint: self.base_mint.to_account_info(),
+                        authority: presale_config.to_account_info(),
+                    },
+                )
+                .with_signer(signer_seeds),
+                payment_amount,
+                self.base_mint.decimals,
+            )?;
+        }
 
-        presale_config.total_return_amount += amount;
+        presale_config.total_return_amount =
+            checked_add(presale_config.total_return_amount, amount)?;
 
         // update allocation
-        user_allocation.amounts[round as usize] = user_allocation.amounts[round as usize] - amount;
-        // user_allocation.base_amount = user_allocation.base_amount + base_amount;
+        user_allocation.amounts[round as usize] =
+            checked_sub(user_allocation.amounts[round as usize], amount)?;
+
+        Ok(PretokenSold {
+            presale: presale_config.key(),
+            user: self.user.key(),
+            round,
+            pretoken_amount: amount,
+            base_amount: return_base_amount,
+            fee_amount,
+            payment_mint: self.base_mint.key(),
+            payment_amount,
+            remained_amount: new_remained_amount,
+            total_buy_amount: presale_config.total_buy_amount,
+            total_return_amount: presale_config.total_return_amount,
+            user_amount: user_allocation.total_amount()?,
+            user_base_amount: user_allocation.base_amount,
+        })
+    }
+
+    /// Base rewards are paid out of the `base_mint` vault, so purchases made
+    /// with another payment mint only earn pretoken rewards.
+    fn referral_base_amount(&self, base_amount: u64) -> u64 {
+        if self.base_mint.key() == self.presale_config.base_mint {
+            base_amount
+        } else {
+            0
+        }
+    }
+
+    /// Credits the referrer for a purchase. The first referred purchase fixes
+    /// the referrer, later purchases and sells must pass the same one.
+    pub fn reward_referrer(
+        &mut self,
+        pretoken_amount: u64,
+        base_amount: u64,
+    ) -> Result<Option<ReferralRewarded>> {
+        if self.presale_config.referral.reward_percent == 0 {
+            return Ok(None);
+        }
+
+        let base_amount = self.referral_base_amount(base_amount);
+        let user_allocation = &mut self.user_allocation;
+        let Some(referrer_stats) = self.referrer_stats.as_mut() else {
+            if user_allocation.referrer.is_some() {
+                return Err(PresaleTokenError::InvalidReferrer.into());
+            }
+            return Ok(None);
+        };
+
+        if referrer_stats.referrer == self.user.key() {
+            return Err(PresaleTokenError::SelfReferral.into());
+        }
+        match user_allocation.referrer {
+            Some(referrer) if referrer != referrer_stats.referrer => {
+                return Err(PresaleTokenError::InvalidReferrer.into());
+            }
+            Some(_) => {}
+            None => {
+                user_allocation.referrer = Some(referrer_stats.referrer);
+                if referrer_stats.referral_count == 0 {
+                    self.presale_config.referrer_count =
+                        checked_add(self.presale_config.referrer_count, 1)?;
+                }
+                referrer_stats.referral_count = checked_add(referrer_stats.referral_count, 1)?;
+            }
+        }
+
+        let presale_config = &mut self.presale_config;
+        let (base_reward_amount, pretoken_reward_amount) = presale_config
+            .referral
+            .reward(pretoken_amount, base_amount)?;
+
+        user_allocation.referred_amount =
+            checked_add(user_allocation.referred_amount, pretoken_amount)?;
+        user_allocation.referral_base_reward_amount = checked_add(
+            user_allocation.referral_base_reward_amount,
+            base_reward_amount,
+        )?;
+        user_allocation.referral_pretoken_reward_amount = checked_add(
+            user_allocation.referral_pretoken_reward_amount,
+            pretoken_reward_amount,
+        )?;
+        referrer_stats.referred_amount =
+            checked_add(referrer_stats.referred_amount, pretoken_amount)?;
+        referrer_stats.base_reward_amount =
+            checked_add(referrer_stats.base_reward_amount, base_reward_amount)?;
+        referrer_stats.pretoken_reward_amount = checked_add(
+            referrer_stats.pretoken_reward_amount,
+            pretoken_reward_amount,
+        )?;
+        presale_config.referral_base_amount =
+            checked_add(presale_config.referral_base_amount, base_reward_amount)?;
+        presale_config.referral_pretoken_amount = checked_add(
+            presale_config.referral_pretoken_amount,
+            pretoken_reward_amount,
+        )?;
+        // pretoken rewards are minted out of the round supply, not on top of
+        // the total supply
+        if checked_add(
+            presale_config.total_buy_amount,
+            presale_config.referral_pretoken_amount,
+        )? > presale_config.round_supply_amount()?
+        {
+            return Err(PresaleTokenError::InsufficientReferralReserve.into());
+        }
+
+        Ok(Some(ReferralRewarded {
+            presale: presale_config.key(),
+            user: self.user.key(),
+            referrer: referrer_stats.referrer,
+            pretoken_amount,
+            base_reward_amount,
+            pretoken_reward_amount,
+        }))
+    }
+
+    /// Takes back the referral reward of the pretokens being sold. Referred
+    /// pretokens are sold first, and each gives back its share of what this
+    /// allocation credited the referrer.
+    pub fn revoke_referral_reward(&mut self, pretoken_amount: u64) -> Result<()> {
+        let user_allocation = &mut self.user_allocation;
+        let revoked_amount = std::cmp::min(pretoken_amount, user_allocation.referred_amount);
+        if revoked_amount == 0 {
+            return Ok(());
+        }
+        let referrer_stats = self
+            .referrer_stats
+            .as_mut()
+            .filter(|referrer_stats| Some(referrer_stats.referrer) == user_allocation.referrer)
+            .ok_or(PresaleTokenError::InvalidReferrer)?;
+
+        let base_reward_amount = mul_div_floor(
+            user_allocation.referral_base_reward_amount,
+            revoked_amount,
+            user_allocation.referred_amount,
+        )?;
+        let pretoken_reward_amount = mul_div_floor(
+            user_allocation.referral_pretoken_reward_amount,
+            revoked_amount,
+            user_allocation.referred_amount,
+        )?;
+
+        user_allocation.referred_amount =
+            checked_sub(user_allocation.referred_amount, revoked_amount)?;
+        user_allocation.referral_base_reward_amount = checked_sub(
+            user_allocation.referral_base_reward_amount,
+            base_reward_amount,
+        )?;
+        user_allocation.referral_pretoken_reward_amount = checked_sub(
+            user_allocation.referral_pretoken_reward_amount,
+            pretoken_reward_amount,
+        )?;
+        referrer_stats.referred_amount =
+            checked_sub(referrer_stats.referred_amount, revoked_amount)?;
+        referrer_stats.base_reward_amount =
+            checked_sub(referrer_stats.base_reward_amount, base_reward_amount)?;
+        referrer_stats.pretoken_reward_amount = checked_sub(
+            referrer_stats.pretoken_reward_amount,
+            pretoken_reward_amount,
+        )?;
+
+        let presale_config = &mut self.presale_config;
+        presale_config.referral_base_amount =
+            checked_sub(presale_config.referral_base_amount, base_reward_amount)?;
+        presale_config.referral_pretoken_amount = checked_sub(
+            presale_config.referral_pretoken_amount,
+            pretoken_reward_amount,
+        )?;
+
+        Ok(())
+    }
+}
+
+impl<'info> RegisterReferrer<'info> {
+    pub fn register_referrer(&mut self) -> Result<()> {
+        let referrer_stats = &mut self.referrer_stats;
+        referrer_stats.presale = self.presale_config.key();
+        referrer_stats.referrer = self.referrer.key();
 
         Ok(())
     }
 }
 
 impl<'info> FinalizePresale<'info> {
-    pub fn finalize_presale(&mut self) -> Result<()> {
+    pub fn finalize_presale(
+        &mut self,
+        presale_config_bump: u8,
+        liquidity_authority_bump: u8,
+    ) -> Result<PresaleFinalized> {
         let presale_config = &mut self.presale_config;
 
         if presale_config.is_finalized {
@@ -239,44 +1019,449 @@ impl<'info> FinalizePresale<'info> {
         let clock = Clock::get()?;
         let current_timestamp = clock.unix_timestamp as u64;
 
-        let presale_end_timestamp = presale_config.start_timestamp + presale_config.duration;
-        if current_timestamp < presale_end_timestamp {
+        if !presale_config.can_finalize(current_timestamp)? {
             return Err(PresaleTokenError::PresaleNotEnded.into());
         }
         presale_config.is_finalized = true;
+        presale_config.finalized_timestamp = current_timestamp;
+        if presale_config.is_pro_rata {
+            presale_config.settle_pro_rata()?;
+        }
 
-        if presale_config.total_buy_amount >= presale_config.target_amount {
+        if presale_config.total_buy_amount >= presale_config.soft_cap_amount {
             presale_config.is_success = true;
+            presale_config.unsold_amount = checked_sub(
+                presale_config.round_supply_amount()?,
+                checked_add(
+                    presale_config.total_buy_amount,
+                    presale_config.referral_pretoken_amount,
+                )?,
+            )?;
+
+            match presale_config.unsold_action {
+                UnsoldAction::Burn => {}
+                UnsoldAction::ReturnToCreator => self.return_unsold(presale_config_bump)?,
+                UnsoldAction::AddToLiquidity => {
+                    self.presale_config.liquidity_amount = checked_add(
+                        self.presale_config.liquidity_amount,
+                        self.presale_config.unsold_amount,
+                    )?;
+                }
+            }
+
+            self.add_liquidity(presale_config_bump, liquidity_authority_bump)?;
 
-            // TODO: add liquidity token
+            // whatever is left after seeding the pool and reserving the
+            // referral rewards and pro-rata refunds belongs to the creator
+            self.presale_config.proceeds_amount = self.raised_base_amount()?;
         }
 
-        Ok(())
+        let presale_config = &self.presale_config;
+        Ok(PresaleFinalized {
+            presale: presale_config.key(),
+            is_success: presale_config.is_success,
+            total_buy_amount: presale_config.total_buy_amount,
+            unsold_amount: presale_config.unsold_amount,
+            liquidity_amount: presale_config.liquidity_amount,
+            liquidity_base_amount: presale_config.liquidity_base_amount,
+            proceeds_amount: presale_config.proceeds_amount,
+        })
     }
-}
-
-impl<'info> ClaimOrRefund<'info> {
-    pub fn claim_or_refund(&mut self, presale_config_bump: u8) -> Result<()> {
-        let presale_config = &mut self.presale_config;
-        let user_allocation = &mut self.user_allocation;
 
-        if !presale_config.is_finalized {
-            return Err(PresaleTokenError::PresaleNotFinalized.into());
+    fn vault_base_amount(&mut self) -> Result<u64> {
+        if self.presale_config.is_native {
+            vault_sol_amount(&self.vault_sol)
+        } else {
+            self.vault_base_token_account.reload()?;
+            Ok(self.vault_base_token_account.amount)
         }
-        if user_allocation.is_proceeded {
-            return Err(PresaleTokenError::UserAlreadyClaimedOrRefunded.into());
+    }
+
+    fn return_unsold(&mut self, presale_config_bump: u8) -> Result<()> {
+        let unsold_amount = self.presale_config.unsold_amount;
+        if unsold_amount == 0 {
+            return Ok(());
         }
+        let creator_token_account = self
+            .creator_token_account
+            .as_ref()
+            .ok_or(PresaleTokenError::MissingCreatorTokenAccount)?;
 
-        user_allocation.is_proceeded = true;
+        let creator = self.presale_config.creator;
+        let presale_id = self.presale_config.presale_id.to_le_bytes();
+        let signer_seeds: &[&[&[u8]]] = &[&[
+            PRESALE_CONFIG_TAG,
+            creator.as_ref(),
+            &presale_id,
+            &[presale_config_bump],
+        ]];
+
+        mint_to(
+            CpiContext::new(
+                self.token_program_2022.to_account_info(),
+                MintTo {
+                    mint: self.new_mint.to_account_info(),
+                    to: creator_token_account.to_account_info(),
+                    authority: self.presale_config.to_account_info(),
+                },
+            )
+            .with_signer(signer_seeds),
+            unsold_amount,
+        )
+    }
+
+    /// Vault balance net of what is owed to referrers and pro-rata buyers.
+    fn raised_base_amount(&mut self) -> Result<u64> {
+        let vault_amount = self.vault_base_amount()?;
+        let presale_config = &self.presale_config;
+        checked_sub(
+            checked_sub(vault_amount, presale_config.referral_base_amount)?,
+            presale_config.excess_base_amount,
+        )
+    }
+
+    /// Moves exactly the pool's share of pretokens and base tokens to the
+    /// liquidity authority, which alone signs the deposit into the AMM.
+    fn add_liquidity(
+        &mut self,
+        presale_config_bump: u8,
+        liquidity_authority_bump: u8,
+    ) -> Result<()> {
+        let liquidity_amount = self.presale_config.liquidity_amount;
+        let liquidity_base_amount = percent_of(
+            self.raised_base_amount()?,
+            self.presale_config.liquidity_percent,
+        )?;
+        if liquidity_amount == 0 || liquidity_base_amount == 0 {
+            return Ok(());
+        }
+
+        self.presale_config.liquidity_base_amount = liquidity_base_amount;
+
+        let creator = self.presale_config.creator;
+        let presale_id = self.presale_config.presale_id.to_le_bytes();
+        let signer_seeds: &[&[&[u8]]] = &[&[
+            PRESALE_CONFIG_TAG,
+            creator.as_ref(),
+            &presale_id,
+            &[presale_config_bump],
+        ]];
+
+        mint_to(
+            CpiContext::new(
+                self.token_program_2022.to_account_info(),
+                MintTo {
+                    mint: self.new_mint.to_account_info(),
+                    to: self.liquidity_token_account.to_account_info(),
+                    authority: self.presale_config.to_account_info(),
+                },
+            )
+            .with_signer(signer_seeds),
+            liquidity_amount,
+        )?;
+
+        // the pool only takes tokens, so the SOL side is wrapped on the way
+        if self.presale_config.is_native {
+            transfer_from_vault_sol(
+                self.vault_sol.to_account_info(),
+                self.liquidity_base_token_account.to_account_info(),
+                self.system_program.to_account_info(),
+                self.presale_config.key(),
+                self.presale_config.vault_sol_bump,
+                liquidity_base_amount,
+            )?;
+            sync_native(CpiContext::new(
+                self.token_program.to_account_info(),
+                SyncNative {
+                    account: self.liquidity_base_token_account.to_account_info(),
+                },
+            ))?;
+        } else {
+            transfer_checked(
+                CpiContext::new(
+                    self.token_program.to_account_info(),
+                    TransferChecked {
+                        from: self.vault_base_token_account.to_account_info(),
+                        to: self.liquidity_base_token_account.to_account_info(),
+                        mint: self.base_mint.to_account_info(),
+                        authority: self.presale_config.to_account_info(),
+                    },
+                )
+                .with_signer(signer_seeds),
+                liquidity_base_amount,
+                self.base_mint.decimals,
+            )?;
+        }
+
+        let presale = self.presale_config.key();
+        let liquidity_signer_seeds: &[&[&[u8]]] = &[&[
+            LIQUIDITY_AUTHORITY_TAG,
+            presale.as_ref(),
+            &[liquidity_authority_bump],
+        ]];
+
+        amm::create_pool(
+            CpiContext::new(
+                self.amm_program.to_account_info(),
+                amm::CreatePool {
+                    pool: self.amm_pool.to_account_info(),
+                    mint_a: self.new_mint.to_account_info(),
+                    mint_b: self.base_mint.to_account_info(),
+                    vault_a: self.amm_vault_new_token_account.to_account_info(),
+                    vault_b: self.amm_vault_base_token_account.to_account_info(),
+                    lp_mint: self.amm_lp_mint.to_account_info(),
+                    payer: self.creator.to_account_info(),
+                    depositor: self.liquidity_authority.to_account_info(),
+                    depositor_token_a: self.liquidity_token_account.to_account_info(),
+                    depositor_token_b: self.liquidity_base_token_account.to_account_info(),
+                    depositor_lp_token_account: self.lp_token_account.to_account_info(),
+                    token_program_a: self.token_program_2022.to_account_info(),
+                    token_program_b: self.token_program.to_account_info(),
+                    token_program: self.token_program.to_account_info(),
+                    associated_token_program: self.associated_token_program.to_account_info(),
+                    system_program: self.system_program.to_account_info(),
+                },
+            )
+            .with_signer(liquidity_signer_seeds),
+            liquidity_amount,
+            liquidity_base_amount,
+        )?;
+
+        Ok(())
+    }
+}
+
+impl<'info> CancelPresale<'info> {
+    /// The creator can back out until the presale starts, the platform admin
+    /// at any time before finalization.
+    pub fn cancel_presale(&mut self) -> Result<PresaleCancelled> {
+        let presale_config = &mut self.presale_config;
+        let authority = self.authority.key();
+
+        if presale_config.is_finalized {
+            return Err(PresaleTokenError::PresaleAlreadyFinalized.into());
+        }
+
+        let clock = Clock::get()?;
+        let current_timestamp = clock.unix_timestamp as u64;
+        if authority != self.platform_config.admin {
+            if authority != presale_config.creator {
+                return Err(PresaleTokenError::InvalidCreator.into());
+            }
+            if current_timestamp >= presale_config.start_timestamp {
+                return Err(PresaleTokenError::PresaleAlreadyStarted.into());
+            }
+        }
+
+        presale_config.is_cancelled = true;
+        presale_config.is_finalized = true;
+        presale_config.finalized_timestamp = current_timestamp;
+
+        Ok(PresaleCancelled {
+            presale: presale_config.key(),
+            authority,
+            total_buy_amount: presale_config.total_buy_amount,
+        })
+    }
+}
+
+impl<'info> WithdrawProceeds<'info> {
+    pub fn withdraw_proceeds(
+        &mut self,
+        amount: u64,
+        presale_config_bump: u8,
+    ) -> Result<ProceedsWithdrawn> {
+        let presale_config = &mut self.presale_config;
+
+        if !presale_config.is_finalized {
+            return Err(PresaleTokenError::PresaleNotFinalized.into());
+        }
+        if !presale_config.is_success {
+            return Err(PresaleTokenError::PresaleNotSucceeded.into());
+        }
+        if amount == 0 {
+            return Err(PresaleTokenError::InvalidAmount.into());
+        }
+
+        // `amount` is in units of the withdrawn mint
+        let payment_index = presale_config.payment_mint_index(&self.base_mint.key())?;
+        let withdrawn_amount = match payment_index {
+            Some(index) => {
+                let payment_mint = &mut presale_config.payment_mints[index];
+                if amount > payment_mint.vault_amount {
+                    return Err(PresaleTokenError::InsufficientProceeds.into());
+                }
+                payment_mint.vault_amount = checked_sub(payment_mint.vault_amount, amount)?;
+                payment_mint.withdrawn_amount = checked_add(payment_mint.withdrawn_amount, amount)?;
+                payment_mint.withdrawn_amount
+            }
+            None => {
+                if amount
+                    > checked_sub(
+                        presale_config.proceeds_amount,
+                        presale_config.withdrawn_amount,
+                    )?
+                {
+                    return Err(PresaleTokenError::InsufficientProceeds.into());
+                }
+                presale_config.withdrawn_amount =
+                    checked_add(presale_config.withdrawn_amount, amount)?;
+                presale_config.withdrawn_amount
+            }
+        };
+
+        let fee_amount = percent_of(amount, self.platform_config.fee_percent)?;
+        let creator_amount = checked_sub(amount, fee_amount)?;
+
+        let presale_id = presale_config.presale_id.to_le_bytes();
         let signer_seeds: &[&[&[u8]]] = &[&[
             PRESALE_CONFIG_TAG,
             presale_config.creator.as_ref(),
+            &presale_id,
             &[presale_config_bump],
         ]];
 
-        if presale_config.is_success {
-            let total_amount = user_allocation.total_amount();
-            // claim
+        if payment_index.is_none() && presale_config.is_native {
+            // transfer platform commission
+            if fee_amount > 0 {
+                transfer_from_vault_sol(
+                    self.vault_sol.to_account_info(),
+                    self.platform_wallet.to_account_info(),
+                    self.system_program.to_account_info(),
+                    presale_config.key(),
+                    presale_config.vault_sol_bump,
+                    fee_amount,
+                )?;
+            }
+
+            // transfer creator proceeds
+            transfer_from_vault_sol(
+                self.vault_sol.to_account_info(),
+                self.creator.to_account_info(),
+                self.system_program.to_account_info(),
+                presale_config.key(),
+                presale_config.vault_sol_bump,
+                creator_amount,
+            )?;
+        } else {
+            let (Some(creator_base_token_account), Some(platform_fee_token_account)) = (
+                self.creator_base_token_account.as_ref(),
+                self.platform_fee_token_account.as_ref(),
+            ) else {
+                return Err(PresaleTokenError::MissingBaseTokenAccount.into());
+            };
+
+            // transfer platform commission
+            if fee_amount > 0 {
+                transfer_checked(
+                    CpiContext::new(
+                        self.token_program.to_account_info(),
+                        TransferChecked {
+                            from: self.vault_base_token_account.to_account_info(),
+                            to: platform_fee_token_account.to_account_info(),
+                            mint: self.base_mint.to_account_info(),
+                            authority: presale_config.to_account_info(),
+                        },
+                    )
+                    .with_signer(signer_seeds),
+                    fee_amount,
+                    self.base_mint.decimals,
+                )?;
+            }
+
+            // transfer creator proceeds
+            transfer_checked(
+                CpiContext::new(
+                    self.token_program.to_account_info(),
+                    TransferChecked {
+                        from: self.vault_base_token_account.to_account_info(),
+                        to: creator_base_token_account.to_account_info(),
+                        mint: self.base_mint.to_account_info(),
+                        authority: presale_config.to_account_info(),
+                    },
+                )
+                .with_signer(signer_seeds),
+                creator_amount,
+                self.base_mint.decimals,
+            )?;
+        }
+
+        Ok(ProceedsWithdrawn {
+            presale: presale_config.key(),
+            creator: self.creator.key(),
+            payment_mint: self.base_mint.key(),
+            amount: creator_amount,
+            fee_amount,
+            withdrawn_amount,
+        })
+    }
+}
+
+impl<'info> ClaimOrRefund<'info> {
+    pub fn claim(&mut self, presale_config_bump: u8) -> Result<Claimed> {
+        let presale_config = &self.presale_config;
+        let user_allocation = &mut self.user_allocation;
+
+        if !presale_config.is_finalized {
+            return Err(PresaleTokenError::PresaleNotFinalized.into());
+        }
+
+        // the first claim scales pro-rata commitments down to the allocation
+        let excess_base_amount = if presale_config.is_pro_rata && !user_allocation.is_settled {
+            user_allocation.settle_pro_rata(&presale_config.round_configs)?
+        } else {
+            0
+        };
+
+        let clock = Clock::get()?;
+        let vested_amount =
+            presale_config.vested_amount(user_allocation, clock.unix_timestamp as u64)?;
+        let claimable_amount = checked_sub(vested_amount, user_allocation.claimed_amount)?;
+        if claimable_amount == 0 && excess_base_amount == 0 {
+            return Err(PresaleTokenError::NothingToClaim.into());
+        }
+
+        user_allocation.claimed_amount = vested_amount;
+
+        let presale_id = presale_config.presale_id.to_le_bytes();
+        let signer_seeds: &[&[&[u8]]] = &[&[
+            PRESALE_CONFIG_TAG,
+            presale_config.creator.as_ref(),
+            &presale_id,
+            &[presale_config_bump],
+        ]];
+
+        if excess_base_amount > 0 && presale_config.is_native {
+            transfer_from_vault_sol(
+                self.vault_sol.to_account_info(),
+                self.user.to_account_info(),
+                self.system_program.to_account_info(),
+                presale_config.key(),
+                presale_config.vault_sol_bump,
+                excess_base_amount,
+            )?;
+        } else if excess_base_amount > 0 {
+            let user_base_token_account = self
+                .user_base_token_account
+                .as_ref()
+                .ok_or(PresaleTokenError::MissingBaseTokenAccount)?;
+            transfer_checked(
+                CpiContext::new(
+                    self.token_program.to_account_info(),
+                    TransferChecked {
+                        from: self.vault_base_token_account.to_account_info(),
+                        to: user_base_token_account.to_account_info(),
+                        mint: self.base_mint.to_account_info(),
+                        authority: presale_config.to_account_info(),
+                    },
+                )
+                .with_signer(signer_seeds),
+                excess_base_amount,
+                self.base_mint.decimals,
+            )?;
+        }
+
+        if claimable_amount > 0 {
             mint_to(
                 CpiContext::new(
                     self.token_program_2022.to_account_info(),
@@ -287,31 +1472,380 @@ impl<'info> ClaimOrRefund<'info> {
                     },
                 )
                 .with_signer(signer_seeds),
-                total_amount,
+                claimable_amount,
+            )?;
+        }
+
+        Ok(Claimed {
+            presale: presale_config.key(),
+            user: self.user.key(),
+            pretoken_amount: claimable_amount,
+            claimed_amount: user_allocation.claimed_amount,
+            total_amount: user_allocation.total_amount()?,
+            excess_base_amount,
+        })
+    }
+
+    pub fn refund(&mut self, presale_config_bump: u8) -> Result<Refunded> {
+        let presale_config = &mut self.presale_config;
+        let user_allocation = &mut self.user_allocation;
+
+        if !presale_config.is_finalized {
+            return Err(PresaleTokenError::PresaleNotFinalized.into());
+        }
+
+        // refunded with the mint passed in, once per mint paid with
+        let payment_index = presale_config.payment_mint_index(&self.base_mint.key())?;
+        let base_amount = user_allocation.paid_amount(payment_index)?;
+        if base_amount == 0 {
+            return Err(PresaleTokenError::UserAlreadyClaimedOrRefunded.into());
+        }
+
+        let fee_amount = if presale_config.is_cancelled {
+            0
+        } else {
+            percent_of(base_amount, presale_config.fee_percent)?
+        };
+        let refund_amount = checked_sub(base_amount, fee_amount)?;
+
+        user_allocation.sub_paid_amount(payment_index, base_amount)?;
+        let payment_amount = match payment_index {
+            Some(index) => {
+                let payment_mint = &mut presale_config.payment_mints[index];
+                let payment_amount = base_to_payment_floor(refund_amount, payment_mint.rate)?;
+                payment_mint.vault_amount = checked_sub(payment_mint.vault_amount, payment_amount)?;
+                payment_amount
+            }
+            None => refund_amount,
+        };
+
+        let presale_id = presale_config.presale_id.to_le_bytes();
+        let signer_seeds: &[&[&[u8]]] = &[&[
+            PRESALE_CONFIG_TAG,
+            presale_config.creator.as_ref(),
+            &presale_id,
+            &[presale_config_bump],
+        ]];
+
+        if payment_index.is_none() && presale_config.is_native {
+            transfer_from_vault_sol(
+                self.vault_sol.to_account_info(),
+                self.user.to_account_info(),
+                self.system_program.to_account_info(),
+                presale_config.key(),
+                presale_config.vault_sol_bump,
+                payment_amount,
             )?;
         } else {
-            // we don't need refund feature for now
-            return Err(PresaleTokenError::NotImplemented.into());
-
-            // refund
-            // let base_amount = user_allocation.base_amount;
-            // let fee = base_amount * (presale_config.fee_percent as u64) / PERCENT_DENOMINATOR;
-            // let refund_amount = base_amount - fee;
-
-            // transfer_checked(
-            //     CpiContext::new(
-            //         self.token_program.to_account_info(),
-            //         TransferChecked {
-            //             from: self.vault_base_token_account.to_account_info(),
-            //             to: self.user_base_token_account.to_account_info(),
-            //             mint: self.base_mint.to_account_info(),
-            //             authority: self.user.to_account_info(),
-            //         },
-            //     )
-            //     .with_signer(signer_seeds),
-            //     refund_amount,
-            //     self.base_mint.decimals,
-            // )?;
+            let user_base_token_account = self
+                .user_base_token_account
+                .as_ref()
+                .ok_or(PresaleTokenError::MissingBaseTokenAccount)?;
+            transfer_checked(
+                CpiContext::new(
+                    self.token_program.to_account_info(),
+                    TransferChecked {
+                        from: self.vault_base_token_account.to_account_info(),
+                        to: user_base_token_account.to_account_info(),
+                        mint: self.base_mint.to_account_info(),
+                        authority: presale_config.to_account_info(),
+                    },
+                )
+                .with_signer(signer_seeds),
+                payment_amount,
+                self.base_mint.decimals,
+            )?;
+        }
+
+        Ok(Refunded {
+            presale: presale_config.key(),
+            user: self.user.key(),
+            base_amount: refund_amount,
+            fee_amount,
+            payment_mint: self.base_mint.key(),
+            payment_amount,
+        })
+    }
+}
+
+impl<'info> ClaimReferralRewards<'info> {
+    pub fn claim_referral_rewards(
+        &mut self,
+        presale_config_bump: u8,
+    ) -> Result<ReferralRewardsClaimed> {
+        let presale_config = &self.presale_config;
+        let referrer_stats = &mut self.referrer_stats;
+
+        if !presale_config.is_finalized {
+            return Err(PresaleTokenError::PresaleNotFinalized.into());
+        }
+        if !presale_config.is_success {
+            return Err(PresaleTokenError::PresaleNotSucceeded.into());
+        }
+        if referrer_stats.is_claimed
+            || (referrer_stats.base_reward_amount == 0
+                && referrer_stats.pretoken_reward_amount == 0)
+        {
+            return Err(PresaleTokenError::NothingToClaim.into());
+        }
+
+        referrer_stats.is_claimed = true;
+
+        let presale_id = presale_config.presale_id.to_le_bytes();
+        let signer_seeds: &[&[&[u8]]] = &[&[
+            PRESALE_CONFIG_TAG,
+            presale_config.creator.as_ref(),
+            &presale_id,
+            &[presale_config_bump],
+        ]];
+
+        let base_amount = referrer_stats.base_reward_amount;
+        if base_amount > 0 && presale_config.is_native {
+            transfer_from_vault_sol(
+                self.vault_sol.to_account_info(),
+                self.referrer.to_account_info(),
+                self.system_program.to_account_info(),
+                presale_config.key(),
+                presale_config.vault_sol_bump,
+                base_amount,
+            )?;
+        } else if base_amount > 0 {
+            let referrer_base_token_account = self
+                .referrer_base_token_account
+                .as_ref()
+                .ok_or(PresaleTokenError::MissingBaseTokenAccount)?;
+            transfer_checked(
+                CpiContext::new(
+                    self.token_program.to_account_info(),
+                    TransferChecked {
+                        from: self.vault_base_token_account.to_account_info(),
+                        to: referrer_base_token_account.to_account_info(),
+                        mint: self.base_mint.to_account_info(),
+                        authority: presale_config.to_account_info(),
+                    },
+                )
+                .with_signer(signer_seeds),
+                base_amount,
+                self.base_mint.decimals,
+            )?;
+        }
+
+        let pretoken_amount = referrer_stats.pretoken_reward_amount;
+        if pretoken_amount > 0 {
+            mint_to(
+                CpiContext::new(
+                    self.token_program_2022.to_account_info(),
+                    MintTo {
+                        mint: self.new_mint.to_account_info(),
+                        to: self.referrer_token_account.to_account_info(),
+                        authority: presale_config.to_account_info(),
+                    },
+                )
+                .with_signer(signer_seeds),
+                pretoken_amount,
+            )?;
+        }
+
+        Ok(ReferralRewardsClaimed {
+            presale: presale_config.key(),
+            referrer: self.referrer.key(),
+            base_amount,
+            pretoken_amount,
+        })
+    }
+}
+
+impl<'info> CloseUserAllocation<'info> {
+    /// Closable once everything allocated was claimed, or everything paid
+    /// was refunded.
+    pub fn close_user_allocation(&mut self) -> Result<()> {
+        let presale_config = &mut self.presale_config;
+        let user_allocation = &self.user_allocation;
+
+        if !presale_config.is_finalized {
+            return Err(PresaleTokenError::PresaleNotFinalized.into());
+        }
+        let total_amount = user_allocation.total_amount()?;
+        let is_settled = if presale_config.is_success {
+            user_allocation.claimed_amount == total_amount
+                && (!presale_config.is_pro_rata || user_allocation.is_settled || total_amount == 0)
+        } else {
+            user_allocation.base_amount == 0
+        };
+        if !is_settled {
+            return Err(PresaleTokenError::AllocationNotSettled.into());
+        }
+
+        // allocations that never bought were not counted
+        if !user_allocation.amounts.is_empty() {
+            presale_config.participant_count = checked_sub(presale_config.participant_count, 1)?;
+        }
+
+        if let Some(user_token_account) = self.user_token_account.as_ref() {
+            close_account(CpiContext::new(
+                self.token_program_2022.to_account_info(),
+                CloseAccount {
+                    account: user_token_account.to_account_info(),
+                    destination: self.user.to_account_info(),
+                    authority: self.user.to_account_info(),
+                },
+            ))?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<'info> CloseReferrerStats<'info> {
+    pub fn close_referrer_stats(&mut self) -> Result<()> {
+        let presale_config = &mut self.presale_config;
+        let referrer_stats = &self.referrer_stats;
+
+        if !presale_config.is_finalized {
+            return Err(PresaleTokenError::PresaleNotFinalized.into());
+        }
+        if presale_config.is_success
+            && !referrer_stats.is_claimed
+            && (referrer_stats.base_reward_amount != 0
+                || referrer_stats.pretoken_reward_amount != 0)
+        {
+            return Err(PresaleTokenError::RewardsNotClaimed.into());
+        }
+
+        if referrer_stats.referral_count != 0 {
+            presale_config.referrer_count = checked_sub(presale_config.referrer_count, 1)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<'info> ClosePresale<'info> {
+    /// Sweeps what the vaults still hold to the creator and closes them.
+    /// After a failed presale that is the sell and refund fees, which are the
+    /// creator's just as sell fees reach them through `withdraw_proceeds`
+    /// after a successful one; otherwise it is rounding or stray transfers.
+    /// Each payment mint is passed in `payment_accounts` as its mint, vault
+    /// and the creator's token account, in `payment_mints` order.
+    pub fn close_presale(
+        &mut self,
+        payment_accounts: &[AccountInfo<'info>],
+        presale_config_bump: u8,
+    ) -> Result<()> {
+        let presale_config = &self.presale_config;
+
+        if !presale_config.is_finalized {
+            return Err(PresaleTokenError::PresaleNotFinalized.into());
+        }
+        // proceeds go through withdraw_proceeds so the platform takes its fee
+        let is_withdrawn = !presale_config.is_success
+            || (presale_config.withdrawn_amount == presale_config.proceeds_amount
+                && presale_config
+                    .payment_mints
+                    .iter()
+                    .all(|payment_mint| payment_mint.vault_amount == 0));
+        if presale_config.participant_count != 0
+            || presale_config.referrer_count != 0
+            || !is_withdrawn
+        {
+            return Err(PresaleTokenError::PresaleNotSettled.into());
+        }
+        if payment_accounts.len() != presale_config.payment_mints.len() * 3 {
+            return Err(PresaleTokenError::InvalidPaymentVault.into());
+        }
+
+        let presale = presale_config.key();
+        let presale_id = presale_config.presale_id.to_le_bytes();
+        let signer_seeds: &[&[&[u8]]] = &[&[
+            PRESALE_CONFIG_TAG,
+            presale_config.creator.as_ref(),
+            &presale_id,
+            &[presale_config_bump],
+        ]];
+
+        let vault_amount = self.vault_base_token_account.amount;
+        if vault_amount > 0 && !presale_config.is_native {
+            let creator_base_token_account = self
+                .creator_base_token_account
+                .as_ref()
+                .ok_or(PresaleTokenError::MissingBaseTokenAccount)?;
+            transfer_checked(
+                CpiContext::new(
+                    self.token_program.to_account_info(),
+                    TransferChecked {
+                        from: self.vault_base_token_account.to_account_info(),
+                        to: creator_base_token_account.to_account_info(),
+                        mint: self.base_mint.to_account_info(),
+                        authority: presale_config.to_account_info(),
+                    },
+                )
+                .with_signer(signer_seeds),
+                vault_amount,
+                self.base_mint.decimals,
+            )?;
+        }
+
+        // wrapped SOL left in a native vault is unwrapped by closing it
+        let mut vaults = vec![self.vault_base_token_account.to_account_info()];
+        for (payment_mint, accounts) in presale_config
+            .payment_mints
+            .iter()
+            .zip(payment_accounts.chunks(3))
+        {
+            let [mint, payment_vault, creator_payment_token_account] = accounts else {
+                return Err(PresaleTokenError::InvalidPaymentVault.into());
+            };
+            if mint.key() != payment_mint.mint
+                || payment_vault.key() != presale_config.vault_address(&presale, &mint.key())
+            {
+                return Err(PresaleTokenError::InvalidPaymentVault.into());
+            }
+
+            // a vault still holding tokens cannot be closed
+            let vault_amount =
+                TokenAccount::try_deserialize(&mut &payment_vault.try_borrow_data()?[..])?.amount;
+            if vault_amount > 0 {
+                transfer_checked(
+                    CpiContext::new(
+                        self.token_program.to_account_info(),
+                        TransferChecked {
+                            from: payment_vault.clone(),
+                            to: creator_payment_token_account.clone(),
+                            mint: mint.clone(),
+                            authority: presale_config.to_account_info(),
+                        },
+                    )
+                    .with_signer(signer_seeds),
+                    vault_amount,
+                    Mint::try_deserialize(&mut &mint.try_borrow_data()?[..])?.decimals,
+                )?;
+            }
+            vaults.push(payment_vault.clone());
+        }
+        for vault in vaults {
+            close_account(
+                CpiContext::new(
+                    self.token_program.to_account_info(),
+                    CloseAccount {
+                        account: vault,
+                        destination: self.creator.to_account_info(),
+                        authority: presale_config.to_account_info(),
+                    },
+                )
+                .with_signer(signer_seeds),
+            )?;
+        }
+
+        let vault_sol_lamports = self.vault_sol.lamports();
+        if vault_sol_lamports > 0 {
+            transfer_from_vault_sol(
+                self.vault_sol.to_account_info(),
+                self.creator.to_account_info(),
+                self.system_program.to_account_info(),
+                presale,
+                presale_config.vault_sol_bump,
+                vault_sol_lamports,
+            )?;
         }
 
         Ok(())
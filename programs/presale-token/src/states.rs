@@ -2,15 +2,336 @@ use anchor_lang::prelude::*;
 
 use crate::*;
 use constants::*;
+use errors::*;
+use oracle::*;
+use utils::*;
 
 #[account]
 #[derive(Default)]
 pub struct PlatformConfig {
+    pub admin: Pubkey,
+    pub pending_admin: Pubkey,
     pub platform_wallet: Pubkey,
     pub fee_mint: Pubkey,
     pub fee_percent: u16,
     pub fee_amount_normal: u64,
     pub fee_amount_special: u64,
+    /// `fee_amount_special` is charged for new mints whose metadata symbol
+    /// matches any of these.
+    pub special_symbol_rules: Vec<SymbolRule>,
+    /// Owner of the price feeds that USD-priced presales read, see `oracle`.
+    pub oracle_program: Pubkey,
+    /// AMM that successful presales seed their liquidity pool in, see `amm`.
+    pub amm_program: Pubkey,
+}
+
+impl PlatformConfig {
+    pub fn space() -> usize {
+        std::mem::size_of::<PlatformConfig>()
+            + MAX_SYMBOL_RULE_COUNT * (std::mem::size_of::<SymbolRule>() + MAX_SYMBOL_RULE_LEN)
+            + 8
+    }
+
+    pub fn is_special_symbol(&self, symbol: &str) -> bool {
+        self.special_symbol_rules
+            .iter()
+            .any(|rule| rule.matches(symbol))
+    }
+}
+
+#[derive(Copy, Clone, AnchorSerialize, AnchorDeserialize, Default, PartialEq, Eq)]
+pub enum SymbolMatch {
+    Prefix,
+    #[default]
+    Suffix,
+    Exact,
+}
+
+/// Symbols are matched ignoring ASCII case.
+#[derive(Clone, AnchorSerialize, AnchorDeserialize, Default)]
+pub struct SymbolRule {
+    pub kind: SymbolMatch,
+    pub pattern: String,
+}
+
+impl SymbolRule {
+    pub fn validate(&self) -> Result<()> {
+        if self.pattern.is_empty() || self.pattern.len() > MAX_SYMBOL_RULE_LEN {
+            return Err(PresaleTokenError::InvalidSymbolRules.into());
+        }
+
+        Ok(())
+    }
+
+    pub fn matches(&self, symbol: &str) -> bool {
+        let symbol = symbol.to_ascii_lowercase();
+        let pattern = self.pattern.to_ascii_lowercase();
+        match self.kind {
+            SymbolMatch::Prefix => symbol.starts_with(&pattern),
+            SymbolMatch::Suffix => symbol.ends_with(&pattern),
+            SymbolMatch::Exact => symbol == pattern,
+        }
+    }
+}
+
+/// Limits on a single wallet's purchases, in pretoken base units.
+/// A zero value disables the corresponding limit.
+#[derive(Copy, Clone, AnchorSerialize, AnchorDeserialize, Default)]
+pub struct PurchaseLimits {
+    pub min_amount: u64,
+    pub max_amount_per_wallet: u64,
+    pub max_amount_per_tx: u64,
+}
+
+impl PurchaseLimits {
+    pub fn validate(&self) -> Result<()> {
+        if (self.max_amount_per_wallet != 0 && self.min_amount > self.max_amount_per_wallet)
+            || (self.max_amount_per_tx != 0 && self.min_amount > self.max_amount_per_tx)
+        {
+            return Err(PresaleTokenError::InvalidPurchaseLimits.into());
+        }
+
+        Ok(())
+    }
+
+    /// `amount_limit` is what is left to buy; taking all of it is allowed
+    /// even below the minimum so a round can always sell out.
+    pub fn check(&self, amount: u64, amount_limit: u64, wallet_amount: u64) -> Result<()> {
+        if self.max_amount_per_tx != 0 && amount > self.max_amount_per_tx {
+            return Err(PresaleTokenError::ExceedsMaxPerTransaction.into());
+        }
+        if amount < self.min_amount && amount < amount_limit {
+            return Err(PresaleTokenError::BelowMinPurchase.into());
+        }
+        if self.max_amount_per_wallet != 0
+            && checked_add(wallet_amount, amount)? > self.max_amount_per_wallet
+        {
+            return Err(PresaleTokenError::ExceedsMaxPerWallet.into());
+        }
+
+        Ok(())
+    }
+}
+
+/// Unlock schedule for claimed pretokens, measured from finalization (TGE).
+/// `tge_percent` unlocks at TGE, the rest vests linearly over
+/// `vesting_duration` once `cliff_duration` has passed.
+#[derive(Copy, Clone, AnchorSerialize, AnchorDeserialize, Default)]
+pub struct VestingSchedule {
+    pub tge_percent: u16,
+    pub cliff_duration: u64,
+    pub vesting_duration: u64,
+}
+
+impl VestingSchedule {
+    pub fn validate(&self) -> Result<()> {
+        if self.tge_percent as u64 > PERCENT_DENOMINATOR {
+            return Err(PresaleTokenError::InvalidVestingSchedule.into());
+        }
+
+        Ok(())
+    }
+
+    pub fn vested_amount(
+        &self,
+        total_amount: u64,
+        tge_timestamp: u64,
+        timestamp: u64,
+    ) -> Result<u64> {
+        if timestamp < tge_timestamp {
+            return Ok(0);
+        }
+
+        let tge_amount = percent_of(total_amount, self.tge_percent)?;
+        let elapsed = timestamp - tge_timestamp;
+        if elapsed < self.cliff_duration {
+            return Ok(tge_amount);
+        }
+
+        let vesting_elapsed = elapsed - self.cliff_duration;
+        if vesting_elapsed >= self.vesting_duration {
+            return Ok(total_amount);
+        }
+
+        let locked_amount = checked_sub(total_amount, tge_amount)?;
+        checked_add(
+            tge_amount,
+            mul_div_floor(locked_amount, vesting_elapsed, self.vesting_duration)?,
+        )
+    }
+}
+
+#[derive(Copy, Clone, AnchorSerialize, AnchorDeserialize, Default, PartialEq, Eq)]
+pub enum ReferralRewardKind {
+    /// Paid out of the vault in base tokens.
+    #[default]
+    BaseToken,
+    /// Minted as extra pretokens on top of the round allocations.
+    Pretoken,
+}
+
+/// What happens to the round supply left unsold by a successful presale.
+#[derive(Copy, Clone, AnchorSerialize, AnchorDeserialize, Default, PartialEq, Eq)]
+pub enum UnsoldAction {
+    /// Never minted.
+    #[default]
+    Burn,
+    /// Minted to the creator at finalization.
+    ReturnToCreator,
+    /// Minted into the liquidity pool next to `liquidity_amount`.
+    AddToLiquidity,
+}
+
+/// Referral reward of `reward_percent` of each referred purchase.
+/// A zero `reward_percent` disables referrals.
+#[derive(Copy, Clone, AnchorSerialize, AnchorDeserialize, Default)]
+pub struct ReferralConfig {
+    pub reward_percent: u16,
+    pub reward_kind: ReferralRewardKind,
+}
+
+impl ReferralConfig {
+    pub fn validate(&self) -> Result<()> {
+        if self.reward_percent as u64 > PERCENT_DENOMINATOR {
+            return Err(PresaleTokenError::InvalidReferralConfig.into());
+        }
+
+        Ok(())
+    }
+
+    /// Returns the `(base, pretoken)` reward for a purchase.
+    pub fn reward(&self, pretoken_amount: u64, base_amount: u64) -> Result<(u64, u64)> {
+        match self.reward_kind {
+            ReferralRewardKind::BaseToken => Ok((percent_of(base_amount, self.reward_percent)?, 0)),
+            ReferralRewardKind::Pretoken => {
+                Ok((0, percent_of(pretoken_amount, self.reward_percent)?))
+            }
+        }
+    }
+}
+
+/// Prices rounds in USD against a `base_mint`/USD price feed. Round prices
+/// are then USD per whole pretoken, scaled by `PRECISION`.
+#[derive(Copy, Clone, AnchorSerialize, AnchorDeserialize, Default)]
+pub struct OracleConfig {
+    pub price_feed: Pubkey,
+    pub max_price_age: u64,
+    pub max_confidence_percent: u16,
+}
+
+impl OracleConfig {
+    pub fn validate(&self) -> Result<()> {
+        if self.max_confidence_percent as u64 > PERCENT_DENOMINATOR {
+            return Err(PresaleTokenError::InvalidOracleConfig.into());
+        }
+
+        Ok(())
+    }
+
+    /// Converts `usd_price` into base units per pretoken unit scaled by
+    /// `PRECISION`, the same unit as a fixed round price.
+    pub fn base_price(
+        &self,
+        oracle_price: &OraclePrice,
+        usd_price: u64,
+        decimals: u8,
+        base_decimals: u8,
+        current_timestamp: u64,
+        round_up: bool,
+    ) -> Result<u64> {
+        if oracle_price.price <= 0 {
+            return Err(PresaleTokenError::InvalidOraclePrice.into());
+        }
+        let publish_time = u64::try_from(oracle_price.publish_time)
+            .map_err(|_| PresaleTokenError::InvalidOraclePrice)?;
+        if checked_add(publish_time, self.max_price_age)? < current_timestamp {
+            return Err(PresaleTokenError::StalePrice.into());
+        }
+        let feed_price = oracle_price.price as u64;
+        if oracle_price.conf > percent_of(feed_price, self.max_confidence_percent)? {
+            return Err(PresaleTokenError::PriceConfidenceTooWide.into());
+        }
+
+        // usd_price * 10^base_decimals / (feed_price * 10^exponent * 10^decimals)
+        let pow10 = |exponent: u32| {
+            10_u128
+                .checked_pow(exponent)
+                .ok_or(PresaleTokenError::MathOverflow)
+        };
+        let mut numerator = (usd_price as u128)
+            .checked_mul(pow10(base_decimals as u32)?)
+            .ok_or(PresaleTokenError::MathOverflow)?;
+        let mut denominator = (feed_price as u128)
+            .checked_mul(pow10(decimals as u32)?)
+            .ok_or(PresaleTokenError::MathOverflow)?;
+        if oracle_price.exponent < 0 {
+            numerator = numerator
+                .checked_mul(pow10(oracle_price.exponent.unsigned_abs())?)
+                .ok_or(PresaleTokenError::MathOverflow)?;
+        } else {
+            denominator = denominator
+                .checked_mul(pow10(oracle_price.exponent as u32)?)
+                .ok_or(PresaleTokenError::MathOverflow)?;
+        }
+
+        let price = if round_up {
+            numerator.div_ceil(denominator)
+        } else {
+            numerator / denominator
+        };
+        u64::try_from(price).map_err(|_| PresaleTokenError::MathOverflow.into())
+    }
+}
+
+/// Proof that the buyer is in a round's allowlist, see `merkle::leaf_hash`.
+#[derive(Clone, AnchorSerialize, AnchorDeserialize)]
+pub struct AllowlistProof {
+    pub cap: u64,
+    pub proof: Vec<[u8; 32]>,
+}
+
+/// Supply amounts are in whole tokens and get scaled by `10^decimals`.
+/// `round_pricing_modes`, `round_purchase_limits`, `round_merkle_roots` and
+/// `round_vestings` are either empty or have one entry per round.
+#[derive(Clone, AnchorSerialize, AnchorDeserialize)]
+pub struct InitializePresaleParams {
+    pub name: String,
+    pub symbol: String,
+    pub uri: String,
+    pub decimals: u8,
+    pub start_timestamp: u64,
+    pub duration: u64,
+    pub total_supply: u64,
+    pub round_amounts: Vec<u64>,
+    pub round_prices: Vec<u64>,
+    pub round_end_times: Vec<u64>,
+    pub round_pricing_modes: Vec<PricingMode>,
+    pub purchase_limits: PurchaseLimits,
+    pub round_purchase_limits: Vec<PurchaseLimits>,
+    pub round_merkle_roots: Vec<Option<[u8; 32]>>,
+    pub vesting: VestingSchedule,
+    pub round_vestings: Vec<Option<VestingSchedule>>,
+    pub referral: ReferralConfig,
+    pub oracle: Option<OracleConfig>,
+    pub is_pro_rata: bool,
+    pub soft_cap: u64,
+    pub hard_cap: u64,
+    pub unsold_action: UnsoldAction,
+    pub sellout_cooldown: u64,
+    pub liquidity_amount: u64,
+    pub fee_percent: u16,
+    pub liquidity_percent: u16,
+}
+
+/// How the price moves inside a round. `Linear` rises from the round `price`
+/// to `end_price` as the round's amount sells.
+#[derive(Copy, Clone, AnchorSerialize, AnchorDeserialize, Default, PartialEq, Eq)]
+pub enum PricingMode {
+    #[default]
+    Fixed,
+    Linear {
+        end_price: u64,
+    },
 }
 
 #[derive(Copy, Clone, AnchorSerialize, AnchorDeserialize, Default)]
@@ -19,38 +340,472 @@ pub struct RoundConfig {
     pub round_total_amount: u64,
     pub remained_amount: u64,
     pub price: u64,
+    pub pricing_mode: PricingMode,
+    pub purchase_limits: PurchaseLimits,
+    pub merkle_root: Option<[u8; 32]>,
+    pub vesting: Option<VestingSchedule>,
+    /// Pretokens and base units committed to the round in pro-rata mode,
+    /// which can exceed `round_total_amount`.
+    pub committed_amount: u64,
+    pub committed_base_amount: u64,
+}
+
+impl RoundConfig {
+    pub fn end_price(&self) -> u64 {
+        match self.pricing_mode {
+            PricingMode::Fixed => self.price,
+            PricingMode::Linear { end_price } => end_price,
+        }
+    }
+
+    pub fn sold_amount(&self) -> u64 {
+        self.round_total_amount.saturating_sub(self.remained_amount)
+    }
+
+    /// Base units for buying `amount` more pretokens, rounded up. `start_price`
+    /// and `end_price` are the round prices in base units, see
+    /// `PresaleConfig::base_price`.
+    pub fn buy_cost(&self, start_price: u64, end_price: u64, amount: u64) -> Result<u64> {
+        if self.pricing_mode == PricingMode::Fixed {
+            return pretoken_to_base_ceil(amount, start_price);
+        }
+
+        let from = self.sold_amount();
+        self.curve_amount(
+            start_price,
+            end_price,
+            from,
+            checked_add(from, amount)?,
+            true,
+        )
+    }
+
+    /// Base units for selling `amount` pretokens back down the curve, rounded
+    /// down. Pretokens bought from returned ones have no position on the
+    /// curve and go at the start price.
+    pub fn sell_value(&self, start_price: u64, end_price: u64, amount: u64) -> Result<u64> {
+        if self.pricing_mode == PricingMode::Fixed {
+            return pretoken_to_base_floor(amount, start_price);
+        }
+
+        let to = self.sold_amount();
+        let from = to.saturating_sub(amount);
+        checked_add(
+            self.curve_amount(start_price, end_price, from, to, false)?,
+            pretoken_to_base_floor(checked_sub(amount, to - from)?, start_price)?,
+        )
+    }
+
+    /// Share of a commitment of `amount` once an oversubscribed round is
+    /// scaled down to `round_total_amount`.
+    pub fn pro_rata_amount(&self, amount: u64) -> Result<u64> {
+        if self.committed_amount <= self.round_total_amount {
+            return Ok(amount);
+        }
+
+        mul_div_floor(amount, self.round_total_amount, self.committed_amount)
+    }
+
+    /// Base units owed back for the part of a commitment of `amount` that
+    /// did not fit, at the round's average committed price. Floored, so the
+    /// refunds of all commitments never exceed that of the whole round.
+    pub fn excess_base_amount(&self, amount: u64) -> Result<u64> {
+        if self.committed_amount <= self.round_total_amount {
+            return Ok(0);
+        }
+
+        let excess_amount = self.committed_amount - self.round_total_amount;
+        mul_div_wide(
+            amount as u128 * excess_amount as u128,
+            self.committed_base_amount as u128,
+            self.committed_amount as u128 * self.committed_amount as u128,
+            false,
+        )
+    }
+
+    /// Integral of the linear price between the positions `from` and `to`;
+    /// past `round_total_amount` the price stays at `end_price`.
+    fn curve_amount(
+        &self,
+        start_price: u64,
+        end_price: u64,
+        from: u64,
+        to: u64,
+        round_up: bool,
+    ) -> Result<u64> {
+        let total_amount = self.round_total_amount as u128;
+        let curve_from = std::cmp::min(from, self.round_total_amount);
+        let curve_to = std::cmp::min(to, self.round_total_amount);
+
+        // the average price over the range is
+        // start + (end - start) * (from + to) / (2 * total)
+        let double_total_amount = total_amount
+            .checked_mul(2)
+            .ok_or(PresaleTokenError::MathOverflow)?;
+        let price_range = checked_sub(end_price, start_price)? as u128;
+        let price_numerator = price_range
+            .checked_mul(curve_from as u128 + curve_to as u128)
+            .and_then(|value| {
+                value.checked_add(double_total_amount.checked_mul(start_price as u128)?)
+            })
+            .ok_or(PresaleTokenError::MathOverflow)?;
+        let denominator = double_total_amount
+            .checked_mul(PRECISION as u128)
+            .ok_or(PresaleTokenError::MathOverflow)?;
+        let curve_amount = mul_div_wide(
+            (curve_to - curve_from) as u128,
+            price_numerator,
+            denominator,
+            round_up,
+        )?;
+
+        let flat_amount = to.saturating_sub(std::cmp::max(from, self.round_total_amount));
+        let flat_amount = if round_up {
+            mul_div_ceil(flat_amount, end_price, PRECISION)?
+        } else {
+            mul_div_floor(flat_amount, end_price, PRECISION)?
+        };
+
+        checked_add(curve_amount, flat_amount)
+    }
+}
+
+/// A mint accepted next to `base_mint`, with its own vault. `rate` is the
+/// amount of `mint` paid per `PRECISION` base units, decimals included.
+#[derive(Copy, Clone, AnchorSerialize, AnchorDeserialize, Default)]
+pub struct PaymentMint {
+    pub mint: Pubkey,
+    pub rate: u64,
+    pub vault_amount: u64,
+    pub withdrawn_amount: u64,
+}
+
+/// Hands out the ids that tell a creator's presales apart.
+#[account]
+#[derive(Default)]
+pub struct CreatorCounter {
+    pub presale_count: u64,
 }
 
 #[account]
 #[derive(Default)]
 pub struct PresaleConfig {
     pub creator: Pubkey,
+    /// Index of the presale among its creator's, part of the PDA seeds.
+    pub presale_id: u64,
     pub base_mint: Pubkey,
+    pub base_decimals: u8,
+    /// Paid in native SOL, held as lamports in the SOL vault.
+    pub is_native: bool,
+    pub vault_sol_bump: u8,
+    pub payment_mints: Vec<PaymentMint>,
     pub new_mint: Pubkey,
     pub decimals: u8,
-    pub round_configs: [RoundConfig; ROUND_COUNT],
+    pub round_configs: Vec<RoundConfig>,
     pub current_round: u8,
+    pub purchase_limits: PurchaseLimits,
+    pub vesting: VestingSchedule,
+    pub referral: ReferralConfig,
+    pub oracle: Option<OracleConfig>,
+    /// Rounds take commitments past their amount and are filled pro-rata
+    /// at finalization.
+    pub is_pro_rata: bool,
     pub start_timestamp: u64,
     pub duration: u64,
     pub liquidity_amount: u64,
-    pub target_amount: u64,
+    pub liquidity_percent: u16,
+    pub liquidity_base_amount: u64,
+    /// Pretokens that must sell for the presale to succeed.
+    pub soft_cap_amount: u64,
+    /// Pretokens that can be sold at most, across all rounds.
+    pub hard_cap_amount: u64,
+    pub unsold_action: UnsoldAction,
+    pub unsold_amount: u64,
+    /// Seconds after selling out to the hard cap before the presale can be
+    /// finalized ahead of its end.
+    pub sellout_cooldown: u64,
+    /// When the hard cap was reached, zero while it is not.
+    pub sellout_timestamp: u64,
+    /// Allocations and rewarded referrers not closed yet; the presale can
+    /// only be closed once both are zero.
+    pub participant_count: u64,
+    pub referrer_count: u64,
     pub total_buy_amount: u64,
     pub total_return_amount: u64,
+    pub referral_base_amount: u64,
+    /// Pretokens reserved out of the round supply for referrers.
+    pub referral_pretoken_amount: u64,
+    /// Base units held back for the excess of pro-rata commitments.
+    pub excess_base_amount: u64,
+    pub proceeds_amount: u64,
+    pub withdrawn_amount: u64,
     pub fee_percent: u16,
+    pub finalized_timestamp: u64,
     pub is_finalized: bool,
     pub is_success: bool,
+    /// Cancelled presales are finalized without success and refund in full.
+    pub is_cancelled: bool,
+}
+
+impl PresaleConfig {
+    pub fn space(round_count: usize) -> usize {
+        std::mem::size_of::<PresaleConfig>()
+            + round_count * std::mem::size_of::<RoundConfig>()
+            + MAX_PAYMENT_MINT_COUNT * std::mem::size_of::<PaymentMint>()
+            + 8
+    }
+
+    /// Index into `payment_mints`, `None` for `base_mint` itself.
+    pub fn payment_mint_index(&self, mint: &Pubkey) -> Result<Option<usize>> {
+        if *mint == self.base_mint {
+            return Ok(None);
+        }
+
+        self.payment_mints
+            .iter()
+            .position(|payment_mint| payment_mint.mint == *mint)
+            .map(Some)
+            .ok_or_else(|| PresaleTokenError::InvalidBaseMint.into())
+    }
+
+    pub fn accepts_mint(&self, mint: &Pubkey) -> bool {
+        self.payment_mint_index(mint).is_ok()
+    }
+
+    /// The vault holding `mint`: the base vault for `base_mint`, otherwise
+    /// the payment mint's own vault.
+    pub fn vault_address(&self, presale: &Pubkey, mint: &Pubkey) -> Pubkey {
+        let (vault, _) = if *mint == self.base_mint {
+            Pubkey::find_program_address(
+                &[VAULT_BASE_TOKEN_ACCOUNT_TAG, presale.as_ref()],
+                &crate::ID,
+            )
+        } else {
+            Pubkey::find_program_address(
+                &[
+                    VAULT_BASE_TOKEN_ACCOUNT_TAG,
+                    presale.as_ref(),
+                    mint.as_ref(),
+                ],
+                &crate::ID,
+            )
+        };
+
+        vault
+    }
+
+    /// Base units per pretoken unit scaled by `PRECISION` for a round priced
+    /// at `price`, converted from USD when the presale has an oracle.
+    pub fn base_price(
+        &self,
+        price: u64,
+        price_feed: Option<&AccountInfo>,
+        oracle_program: &Pubkey,
+        current_timestamp: u64,
+        round_up: bool,
+    ) -> Result<u64> {
+        let Some(oracle) = self.oracle else {
+            return Ok(price);
+        };
+        let oracle_price =
+            load_price::<PythPriceFeed>(price_feed, &oracle.price_feed, oracle_program)?;
+
+        oracle.base_price(
+            &oracle_price,
+            price,
+            self.decimals,
+            self.base_decimals,
+            current_timestamp,
+            round_up,
+        )
+    }
+
+    pub fn round_count(&self) -> usize {
+        self.round_configs.len()
+    }
+
+    /// Pretokens put up for sale across all rounds.
+    pub fn round_supply_amount(&self) -> Result<u64> {
+        self.round_configs.iter().try_fold(0, |sum, round_config| {
+            checked_add(sum, round_config.round_total_amount)
+        })
+    }
+
+    /// Finalization opens at the end of the presale, or once the cooldown
+    /// after a sellout has passed.
+    pub fn can_finalize(&self, current_timestamp: u64) -> Result<bool> {
+        let presale_end_timestamp = checked_add(self.start_timestamp, self.duration)?;
+        if current_timestamp >= presale_end_timestamp {
+            return Ok(true);
+        }
+
+        Ok(self.sellout_timestamp != 0
+            && current_timestamp >= checked_add(self.sellout_timestamp, self.sellout_cooldown)?)
+    }
+
+    pub fn last_round(&self) -> u8 {
+        (self.round_count() - 1) as u8
+    }
+
+    /// A round ends when it sells out or when its `round_end_time` passes,
+    /// whichever comes first; this catches up on the time-based part.
+    pub fn advance_round(&mut self, current_timestamp: u64) {
+        while (self.current_round as usize) < self.round_count()
+            && current_timestamp >= self.round_configs[self.current_round as usize].round_end_time
+        {
+            self.current_round += 1;
+        }
+    }
+
+    /// Caps each round's commitments at its amount and reserves the base
+    /// owed back to the oversubscribed ones.
+    pub fn settle_pro_rata(&mut self) -> Result<()> {
+        let mut total_buy_amount: u64 = 0;
+        let mut excess_base_amount: u64 = 0;
+        for round_config in self.round_configs.iter() {
+            total_buy_amount = checked_add(
+                total_buy_amount,
+                round_config.pro_rata_amount(round_config.committed_amount)?,
+            )?;
+            excess_base_amount = checked_add(
+                excess_base_amount,
+                round_config.excess_base_amount(round_config.committed_amount)?,
+            )?;
+        }
+        self.total_buy_amount = total_buy_amount;
+        self.excess_base_amount = excess_base_amount;
+
+        Ok(())
+    }
+
+    /// Pretokens of `user_allocation` unlocked at `timestamp`, each round
+    /// vesting on its own schedule or the presale default.
+    pub fn vested_amount(&self, user_allocation: &UserAllocation, timestamp: u64) -> Result<u64> {
+        self.round_configs
+            .iter()
+            .zip(user_allocation.amounts.iter())
+            .try_fold(0, |sum, (round_config, amount)| {
+                let vesting = round_config.vesting.unwrap_or(self.vesting);
+                checked_add(
+                    sum,
+                    vesting.vested_amount(*amount, self.finalized_timestamp, timestamp)?,
+                )
+            })
+    }
 }
 
 #[account]
 #[derive(Default)]
 pub struct UserAllocation {
-    pub amounts: [u64; ROUND_COUNT],
-    // pub base_amount: u64, // no need for now
-    pub is_proceeded: bool,
+    pub amounts: Vec<u64>,
+    pub base_amount: u64,
+    /// Part of `base_amount` paid with each of the presale's `payment_mints`,
+    /// the rest was paid with `base_mint`.
+    pub payment_amounts: Vec<u64>,
+    pub claimed_amount: u64,
+    /// Set by the first referred purchase and fixed afterwards.
+    pub referrer: Option<Pubkey>,
+    /// Pretokens bought through `referrer` and not sold since, with the
+    /// rewards still credited to the referrer for them.
+    pub referred_amount: u64,
+    pub referral_base_reward_amount: u64,
+    pub referral_pretoken_reward_amount: u64,
+    /// Commitments were scaled down to the pro-rata allocation.
+    pub is_settled: bool,
 }
 
 impl UserAllocation {
-    pub fn total_amount(&self) -> u64 {
-        self.amounts.iter().sum()
+    pub fn space(round_count: usize) -> usize {
+        std::mem::size_of::<UserAllocation>()
+            + (round_count + MAX_PAYMENT_MINT_COUNT) * std::mem::size_of::<u64>()
+            + 8
+    }
+
+    /// Base amount paid with `payment_index`, see `PresaleConfig::payment_mint_index`.
+    pub fn paid_amount(&self, payment_index: Option<usize>) -> Result<u64> {
+        match payment_index {
+            Some(index) => Ok(self.payment_amounts.get(index).copied().unwrap_or_default()),
+            None => {
+                let payment_amount = self
+                    .payment_amounts
+                    .iter()
+                    .try_fold(0, |sum, amount| checked_add(sum, *amount))?;
+                checked_sub(self.base_amount, payment_amount)
+            }
+        }
     }
+
+    pub fn add_paid_amount(&mut self, payment_index: Option<usize>, amount: u64) -> Result<()> {
+        self.base_amount = checked_add(self.base_amount, amount)?;
+        if let Some(index) = payment_index {
+            if self.payment_amounts.len() <= index {
+                self.payment_amounts.resize(index + 1, 0);
+            }
+            self.payment_amounts[index] = checked_add(self.payment_amounts[index], amount)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn sub_paid_amount(&mut self, payment_index: Option<usize>, amount: u64) -> Result<()> {
+        // a dust sell can round down to nothing in a mint never paid with
+        if amount == 0 {
+            return Ok(());
+        }
+        if amount > self.paid_amount(payment_index)? {
+            return Err(PresaleTokenError::InsufficientPaymentAmount.into());
+        }
+
+        self.base_amount = checked_sub(self.base_amount, amount)?;
+        if let Some(index) = payment_index {
+            self.payment_amounts[index] = checked_sub(self.payment_amounts[index], amount)?;
+        }
+
+        Ok(())
+    }
+
+    /// Scales the commitments down to their pro-rata share and returns the
+    /// base owed back for the rest.
+    pub fn settle_pro_rata(&mut self, round_configs: &[RoundConfig]) -> Result<u64> {
+        let mut excess_base_amount: u64 = 0;
+        for (amount, round_config) in self.amounts.iter_mut().zip(round_configs.iter()) {
+            excess_base_amount = checked_add(
+                excess_base_amount,
+                round_config.excess_base_amount(*amount)?,
+            )?;
+            *amount = round_config.pro_rata_amount(*amount)?;
+        }
+        // average pricing can owe a buyer who paid below it a unit more
+        excess_base_amount = std::cmp::min(excess_base_amount, self.base_amount);
+        self.base_amount = checked_sub(self.base_amount, excess_base_amount)?;
+        self.is_settled = true;
+
+        Ok(excess_base_amount)
+    }
+
+    /// Allocations created by a sell-only call have no rounds yet.
+    pub fn round_amount(&self, round: u8) -> u64 {
+        self.amounts
+            .get(round as usize)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    pub fn total_amount(&self) -> Result<u64> {
+        self.amounts
+            .iter()
+            .try_fold(0, |sum, amount| checked_add(sum, *amount))
+    }
+}
+
+#[account]
+#[derive(Default)]
+pub struct ReferrerStats {
+    pub presale: Pubkey,
+    pub referrer: Pubkey,
+    pub referral_count: u64,
+    pub referred_amount: u64,
+    pub base_reward_amount: u64,
+    pub pretoken_reward_amount: u64,
+    pub is_claimed: bool,
 }
@@ -1 +1,109 @@
+use anchor_lang::prelude::*;
 
+use crate::*;
+use constants::*;
+use errors::*;
+
+pub fn checked_add(a: u64, b: u64) -> Result<u64> {
+    a.checked_add(b)
+        .ok_or_else(|| PresaleTokenError::MathOverflow.into())
+}
+
+pub fn checked_sub(a: u64, b: u64) -> Result<u64> {
+    a.checked_sub(b)
+        .ok_or_else(|| PresaleTokenError::MathUnderflow.into())
+}
+
+pub fn checked_mul(a: u64, b: u64) -> Result<u64> {
+    a.checked_mul(b)
+        .ok_or_else(|| PresaleTokenError::MathOverflow.into())
+}
+
+pub fn checked_pow10(exponent: u8) -> Result<u64> {
+    10_u64
+        .checked_pow(exponent as u32)
+        .ok_or_else(|| PresaleTokenError::MathOverflow.into())
+}
+
+/// `a * b / denominator` rounded down, computed in u128.
+pub fn mul_div_floor(a: u64, b: u64, denominator: u64) -> Result<u64> {
+    let value = (a as u128)
+        .checked_mul(b as u128)
+        .and_then(|value| value.checked_div(denominator as u128))
+        .ok_or(PresaleTokenError::MathOverflow)?;
+    u64::try_from(value).map_err(|_| PresaleTokenError::MathOverflow.into())
+}
+
+/// `a * b / denominator` rounded up, computed in u128.
+pub fn mul_div_ceil(a: u64, b: u64, denominator: u64) -> Result<u64> {
+    let value = (a as u128)
+        .checked_mul(b as u128)
+        .and_then(|value| value.checked_add((denominator as u128).checked_sub(1)?))
+        .and_then(|value| value.checked_div(denominator as u128))
+        .ok_or(PresaleTokenError::MathOverflow)?;
+    u64::try_from(value).map_err(|_| PresaleTokenError::MathOverflow.into())
+}
+
+/// `a * b / denominator` with a 256-bit intermediate product, for results
+/// that fit u64 but whose product does not fit u128.
+pub fn mul_div_wide(a: u128, b: u128, denominator: u128, round_up: bool) -> Result<u64> {
+    const MASK: u128 = u64::MAX as u128;
+    if denominator == 0 {
+        return Err(PresaleTokenError::MathOverflow.into());
+    }
+
+    let (a_hi, a_lo) = (a >> 64, a & MASK);
+    let (b_hi, b_lo) = (b >> 64, b & MASK);
+    let lo_lo = a_lo * b_lo;
+    let hi_lo = a_hi * b_lo;
+    let lo_hi = a_lo * b_hi;
+    let cross = (lo_lo >> 64) + (hi_lo & MASK) + (lo_hi & MASK);
+    let lo = (cross << 64) | (lo_lo & MASK);
+    let hi = a_hi * b_hi + (hi_lo >> 64) + (lo_hi >> 64) + (cross >> 64);
+    if hi >= denominator {
+        return Err(PresaleTokenError::MathOverflow.into());
+    }
+
+    // shift-subtract division of the 256-bit (hi, lo) by denominator
+    let mut remainder = hi;
+    let mut quotient: u128 = 0;
+    for i in (0..128).rev() {
+        let carry = remainder >> 127;
+        remainder = (remainder << 1) | ((lo >> i) & 1);
+        quotient <<= 1;
+        if carry == 1 || remainder >= denominator {
+            remainder = remainder.wrapping_sub(denominator);
+            quotient |= 1;
+        }
+    }
+    if round_up && remainder > 0 {
+        quotient += 1;
+    }
+
+    u64::try_from(quotient).map_err(|_| PresaleTokenError::MathOverflow.into())
+}
+
+/// Base tokens a buyer pays for `amount` pretokens, rounded in the protocol's favour.
+pub fn pretoken_to_base_ceil(amount: u64, price: u64) -> Result<u64> {
+    mul_div_ceil(amount, price, PRECISION)
+}
+
+/// Base tokens a seller gets back for `amount` pretokens, rounded in the protocol's favour.
+pub fn pretoken_to_base_floor(amount: u64, price: u64) -> Result<u64> {
+    mul_div_floor(amount, price, PRECISION)
+}
+
+/// Payment mint tokens a buyer pays for `amount` base units, rounded in the protocol's favour.
+pub fn base_to_payment_ceil(amount: u64, rate: u64) -> Result<u64> {
+    mul_div_ceil(amount, rate, PRECISION)
+}
+
+/// Payment mint tokens paid out for `amount` base units, rounded in the protocol's favour.
+pub fn base_to_payment_floor(amount: u64, rate: u64) -> Result<u64> {
+    mul_div_floor(amount, rate, PRECISION)
+}
+
+/// `percent` of `amount` in basis points of `PERCENT_DENOMINATOR`, rounded down.
+pub fn percent_of(amount: u64, percent: u16) -> Result<u64> {
+    mul_div_floor(amount, percent as u64, PERCENT_DENOMINATOR)
+}
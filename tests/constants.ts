@@ -2,10 +2,13 @@ import * as anchor from "@coral-xyz/anchor";
 
 export const PRECISION = new anchor.BN(1_000_000_000);
 export const PERCENT_DENOMINATOR = 10_000;
-export const ROUND_COUNT = 4;
+export const MAX_ROUND_COUNT = 10;
 
 export const PLATFORM_CONFIG_TAG = Buffer.from("platform-config");
 export const PRESALE_CONFIG_TAG = Buffer.from("presale-config");
+export const CREATOR_COUNTER_TAG = Buffer.from("creator-counter");
+export const NEW_MINT_TAG = Buffer.from("new-mint");
+export const VAULT_SOL_TAG = Buffer.from("vault-sol");
 export const VAULT_BASE_TOKEN_ACCOUNT_TAG = Buffer.from(
   "vault-base-token-account"
 );
@@ -14,3 +17,16 @@ export const USER_TOKEN_ACCOUNT_TAG = Buffer.from("user-token-account");
 export const PLATFORM_FEE_TOKEN_ACCOUNT_TAG = Buffer.from(
   "platform-fee-token-account"
 );
+export const LIQUIDITY_TOKEN_ACCOUNT_TAG = Buffer.from(
+  "liquidity-token-account"
+);
+export const LIQUIDITY_BASE_TOKEN_ACCOUNT_TAG = Buffer.from(
+  "liquidity-base-token-account"
+);
+export const LIQUIDITY_AUTHORITY_TAG = Buffer.from("liquidity-authority");
+export const REFERRER_STATS_TAG = Buffer.from("referrer-stats");
+
+export const POOL_TAG = Buffer.from("pool");
+export const POOL_VAULT_TAG = Buffer.from("pool-vault");
+export const LP_MINT_TAG = Buffer.from("lp-mint");
+export const PRICE_FEED_TAG = Buffer.from("price-feed");
@@ -4,39 +4,54 @@ import {
   Keypair,
   PublicKey,
   SystemProgram,
+  SYSVAR_CLOCK_PUBKEY,
   Transaction,
   sendAndConfirmTransaction,
 } from "@solana/web3.js";
-import { pack, TokenMetadata } from "@solana/spl-token-metadata";
 import { PresaleToken } from "../target/types/presale_token";
-import { pda, safeAirdrop } from "./utils";
+import { MockAmm } from "../target/types/mock_amm";
+import { MockOracle } from "../target/types/mock_oracle";
+import {
+  buildAllowlist,
+  getCpiEvents,
+  pda,
+  presaleIdSeed,
+  safeAirdrop,
+} from "./utils";
 import {
   ASSOCIATED_TOKEN_PROGRAM_ID,
-  AuthorityType,
   createAssociatedTokenAccountInstruction,
-  createInitializeInstruction,
-  createInitializeMetadataPointerInstruction,
-  createInitializeMintInstruction,
   createMint,
   createMintToInstruction,
-  createSetAuthorityInstruction,
-  ExtensionType,
+  getOrCreateAssociatedTokenAccount,
   getAccount,
   getAssociatedTokenAddressSync,
-  getMintLen,
-  LENGTH_SIZE,
+  getMint,
+  getTokenMetadata,
+  mintTo,
+  NATIVE_MINT,
   TOKEN_2022_PROGRAM_ID,
   TOKEN_PROGRAM_ID,
-  TYPE_SIZE,
 } from "@solana/spl-token";
 import {
+  CREATOR_COUNTER_TAG,
+  LIQUIDITY_AUTHORITY_TAG,
+  LIQUIDITY_BASE_TOKEN_ACCOUNT_TAG,
+  LIQUIDITY_TOKEN_ACCOUNT_TAG,
+  LP_MINT_TAG,
+  NEW_MINT_TAG,
   PLATFORM_CONFIG_TAG,
   PLATFORM_FEE_TOKEN_ACCOUNT_TAG,
+  POOL_TAG,
+  POOL_VAULT_TAG,
   PRECISION,
+  PRICE_FEED_TAG,
   PRESALE_CONFIG_TAG,
+  REFERRER_STATS_TAG,
   USER_ALLOCATION_TAG,
   USER_TOKEN_ACCOUNT_TAG,
   VAULT_BASE_TOKEN_ACCOUNT_TAG,
+  VAULT_SOL_TAG,
 } from "./constants";
 import { assert } from "chai";
 
@@ -47,6 +62,8 @@ describe("presale-token", () => {
   const program = anchor.workspace.PresaleToken as Program<PresaleToken>;
 
   const programId = program.programId;
+  const ammProgram = anchor.workspace.MockAmm as Program<MockAmm>;
+  const oracleProgram = anchor.workspace.MockOracle as Program<MockOracle>;
 
   const wallet = provider.wallet as anchor.Wallet;
   const platformWallet = wallet.publicKey;
@@ -70,8 +87,7 @@ describe("presale-token", () => {
 
   let feeMint: PublicKey; // USDC
   let baseMint: PublicKey;
-  const newMintAuth = new Keypair();
-  const newMint = newMintAuth.publicKey;
+  let paymentMint: PublicKey; // USDT, accepted next to baseMint
 
   it("Prepare!", async () => {
     await Promise.all([
@@ -168,81 +184,50 @@ describe("presale-token", () => {
       return tokenAccount;
     });
 
-    const metaData: TokenMetadata = {
-      updateAuthority: admin,
-      mint: newMint,
-      name: "Hello Token",
-      symbol: "HELLO",
-      uri: "",
-      additionalMetadata: [],
-    };
-
-    // Size of MetadataExtension 2 bytes for type, 2 bytes for length
-    const metadataExtension = TYPE_SIZE + LENGTH_SIZE;
-    // Size of metadata
-    const metadataLen = pack(metaData).length;
-
-    const extensions = [ExtensionType.MetadataPointer];
-    const mintLen = getMintLen(extensions);
-    const lamports =
-      await provider.connection.getMinimumBalanceForRentExemption(
-        mintLen + metadataExtension + metadataLen
-      );
-
-    transaction.add(
-      SystemProgram.createAccount({
-        fromPubkey: admin,
-        newAccountPubkey: newMint,
-        space: mintLen,
-        lamports: lamports,
-        programId: TOKEN_2022_PROGRAM_ID,
-      }),
-      createInitializeMetadataPointerInstruction(
-        newMint, // Mint Account address
-        admin, // Authority that can set the metadata address
-        newMint, // Account address that holds the metadata
-        TOKEN_2022_PROGRAM_ID
-      ),
-      createInitializeMintInstruction(
-        newMint,
-        9,
-        admin,
-        null,
-        TOKEN_2022_PROGRAM_ID
-      ),
-      createInitializeInstruction({
-        programId: TOKEN_2022_PROGRAM_ID, // Token Extension Program as Metadata Program
-        metadata: newMint, // Account address that holds the metadata
-        updateAuthority: admin, // Authority that can update the metadata
-        mint: newMint, // Mint Account address
-        mintAuthority: admin, // Designated Mint Authority
-        name: metaData.name,
-        symbol: metaData.symbol,
-        uri: metaData.uri,
-      }),
-      createSetAuthorityInstruction(
-        newMint, // account: PublicKey,
-        admin, // currentAuthority: PublicKey,
-        AuthorityType.MintTokens, // authorityType: AuthorityType,
-        presaleConfig, // newAuthority: PublicKey | null,
-        [], // multiSigners: (Signer | PublicKey)[] = [],
-        TOKEN_2022_PROGRAM_ID // programId = TOKEN_PROGRAM_ID
-      )
-    );
-
     const txSig = await sendAndConfirmTransaction(
       connection,
       transaction,
-      [wallet.payer, newMintAuth, adminKeypair],
+      [wallet.payer],
       { skipPreflight: true }
     );
     console.log(`Transaction Signature: ${txSig}`);
   });
 
   const configData = {
+    name: "Hello Token",
+    symbol: "HELLO",
+    uri: "",
     decimals: 9,
     startTime: new anchor.BN(0), // not set yet
-    duration: new anchor.BN(20), // 20 seconds
+    duration: new anchor.BN(40), // 40 seconds
+    totalSupply: new anchor.BN(1_000_000_000), // 1B token
+    roundAmounts: [
+      new anchor.BN(245_000_000),
+      new anchor.BN(235_000_000),
+      new anchor.BN(215_000_000),
+      new anchor.BN(105_000_000),
+    ],
+    liquidityAmount: new anchor.BN(200_000_000),
+    softCap: new anchor.BN(400_000_000),
+    hardCap: new anchor.BN(800_000_000),
+    // selling out only ends the presale early an hour later
+    selloutCooldown: new anchor.BN(3600),
+    purchaseLimits: {
+      minAmount: new anchor.BN(1_000).mul(PRECISION),
+      maxAmountPerWallet: new anchor.BN(0),
+      maxAmountPerTx: new anchor.BN(0),
+    },
+    // fully unlocked at TGE
+    vesting: {
+      tgePercent: 10000,
+      cliffDuration: new anchor.BN(0),
+      vestingDuration: new anchor.BN(0),
+    },
+    // 5% of referred purchases, paid in base tokens
+    referral: {
+      rewardPercent: 500,
+      rewardKind: { baseToken: {} },
+    },
     roundPrices: [
       new anchor.BN(0.00000003 * PRECISION.toNumber()), // 0.00000003 SOL
       new anchor.BN(0.00000006 * PRECISION.toNumber()), // 0.00000006 SOL
@@ -250,20 +235,78 @@ describe("presale-token", () => {
       new anchor.BN(0.00000036 * PRECISION.toNumber()), // 0.00000036 SOL
     ],
     feePercent: 500, // 5%
+    liquidityPercent: 5000, // 50%
   };
+  // every round lasts 10 seconds unless it sells out earlier
+  let allowlist: ReturnType<typeof buildAllowlist>;
+  const getRoundEndTimes = () =>
+    configData.roundAmounts.map((_, i) =>
+      configData.startTime.addn((i + 1) * 10)
+    );
   const platformConfig = pda([PLATFORM_CONFIG_TAG], programId);
-  const presaleConfig = pda([PRESALE_CONFIG_TAG, admin.toBuffer()], programId);
-  const platformFeeTokenAccount = pda([PLATFORM_FEE_TOKEN_ACCOUNT_TAG], programId);
+  const presaleConfig = pda(
+    [PRESALE_CONFIG_TAG, admin.toBuffer(), presaleIdSeed(0)],
+    programId
+  );
+  const newMint = pda([NEW_MINT_TAG, presaleConfig.toBuffer()], programId);
+  const getPlatformFeeTokenAccount = () =>
+    pda(
+      [
+        PLATFORM_FEE_TOKEN_ACCOUNT_TAG,
+        platformWallet.toBuffer(),
+        feeMint.toBuffer(),
+      ],
+      programId
+    );
   const vaultBaseTokenAccount = pda(
     [VAULT_BASE_TOKEN_ACCOUNT_TAG, presaleConfig.toBuffer()],
     programId
   );
+  const liquidityAuthority = pda(
+    [LIQUIDITY_AUTHORITY_TAG, presaleConfig.toBuffer()],
+    programId
+  );
+  const liquidityTokenAccount = pda(
+    [LIQUIDITY_TOKEN_ACCOUNT_TAG, presaleConfig.toBuffer()],
+    programId
+  );
+  const liquidityBaseTokenAccount = pda(
+    [LIQUIDITY_BASE_TOKEN_ACCOUNT_TAG, presaleConfig.toBuffer()],
+    programId
+  );
+  // baseMint is only known after "Prepare!", so derive the pool lazily
+  const getAmmPool = () =>
+    pda(
+      [POOL_TAG, newMint.toBuffer(), baseMint.toBuffer()],
+      ammProgram.programId
+    );
+  const getAmmVault = (mint: PublicKey) =>
+    pda(
+      [POOL_VAULT_TAG, getAmmPool().toBuffer(), mint.toBuffer()],
+      ammProgram.programId
+    );
+  const getAmmLpMint = () =>
+    pda([LP_MINT_TAG, getAmmPool().toBuffer()], ammProgram.programId);
+  const getLpTokenAccount = () =>
+    getAssociatedTokenAddressSync(
+      getAmmLpMint(),
+      liquidityAuthority,
+      true,
+      TOKEN_PROGRAM_ID,
+      ASSOCIATED_TOKEN_PROGRAM_ID
+    );
   const getUserAllocation = (user: PublicKey) =>
     pda(
       [USER_ALLOCATION_TAG, presaleConfig.toBuffer(), user.toBuffer()],
       programId
     );
 
+  const getReferrerStats = (referrer: PublicKey) =>
+    pda(
+      [REFERRER_STATS_TAG, presaleConfig.toBuffer(), referrer.toBuffer()],
+      programId
+    );
+
   it("Initialize!", async () => {
     const txSig = await program.methods
       .initialize().accounts({
@@ -277,17 +320,405 @@ describe("presale-token", () => {
     console.log(`Transaction Signature: ${txSig}`);
   });
 
+  describe("Platform Admin", () => {
+    it("Update fee amounts - not admin - fail", async () => {
+      try {
+        await program.methods
+          .updateFeeAmounts(0, new anchor.BN(0), new anchor.BN(0))
+          .accounts({ platformConfig, admin: userA })
+          .signers([userAKeypair])
+          .rpc();
+      } catch (err) {
+        assert.equal(err.error.errorMessage, "Invalid admin");
+      }
+    });
+
+    it("Update fee amounts - success", async () => {
+      await program.methods
+        .updateFeeAmounts(
+          0,
+          new anchor.BN(2_000_000),
+          new anchor.BN(10_000_000)
+        )
+        .accounts({ platformConfig, admin: platformWallet })
+        .signers([wallet.payer])
+        .rpc();
+      const platformConfigAccount = await program.account.platformConfig.fetch(
+        platformConfig
+      );
+      assert.equal(
+        platformConfigAccount.feeAmountSpecial.toNumber(),
+        10_000_000,
+        "feeAmountSpecial is wrong"
+      );
+    });
+
+    it("Update special symbol rules - not admin - fail", async () => {
+      try {
+        await program.methods
+          .updateSpecialSymbolRules([])
+          .accounts({ platformConfig, admin: userA })
+          .signers([userAKeypair])
+          .rpc();
+      } catch (err) {
+        assert.equal(err.error.errorMessage, "Invalid admin");
+      }
+    });
+
+    it("Update special symbol rules - success", async () => {
+      // the presale mint's "HELLO" matches neither, so it pays the normal fee
+      await program.methods
+        .updateSpecialSymbolRules([
+          { kind: { suffix: {} }, pattern: "safe" },
+          { kind: { exact: {} }, pattern: "SAFU" },
+        ])
+        .accounts({ platformConfig, admin: platformWallet })
+        .signers([wallet.payer])
+        .rpc();
+      const platformConfigAccount = await program.account.platformConfig.fetch(
+        platformConfig
+      );
+      assert.equal(
+        platformConfigAccount.specialSymbolRules.length,
+        2,
+        "specialSymbolRules is wrong"
+      );
+    });
+
+    it("Update oracle program - success", async () => {
+      await program.methods
+        .updateOracleProgram(oracleProgram.programId)
+        .accounts({ platformConfig, admin: platformWallet })
+        .signers([wallet.payer])
+        .rpc();
+      const platformConfigAccount = await program.account.platformConfig.fetch(
+        platformConfig
+      );
+      assert.equal(
+        platformConfigAccount.oracleProgram.toBase58(),
+        oracleProgram.programId.toBase58(),
+        "oracleProgram is wrong"
+      );
+    });
+
+    it("Update AMM program - not admin - fail", async () => {
+      try {
+        await program.methods
+          .updateAmmProgram(ammProgram.programId)
+          .accounts({ platformConfig, admin: userA })
+          .signers([userAKeypair])
+          .rpc();
+      } catch (err) {
+        assert.equal(err.error.errorMessage, "Invalid admin");
+      }
+    });
+
+    it("Update AMM program - success", async () => {
+      await program.methods
+        .updateAmmProgram(ammProgram.programId)
+        .accounts({ platformConfig, admin: platformWallet })
+        .signers([wallet.payer])
+        .rpc();
+      const platformConfigAccount = await program.account.platformConfig.fetch(
+        platformConfig
+      );
+      assert.equal(
+        platformConfigAccount.ammProgram.toBase58(),
+        ammProgram.programId.toBase58(),
+        "ammProgram is wrong"
+      );
+    });
+
+    it("Transfer admin - propose and accept - success", async () => {
+      await program.methods
+        .proposeAdmin(userA)
+        .accounts({ platformConfig, admin: platformWallet })
+        .signers([wallet.payer])
+        .rpc();
+      await program.methods
+        .acceptAdmin()
+        .accounts({ platformConfig, pendingAdmin: userA })
+        .signers([userAKeypair])
+        .rpc();
+      let platformConfigAccount = await program.account.platformConfig.fetch(
+        platformConfig
+      );
+      assert.equal(
+        platformConfigAccount.admin.toBase58(),
+        userA.toBase58(),
+        "admin is wrong"
+      );
+
+      // hand it back for the rest of the suite
+      await program.methods
+        .proposeAdmin(platformWallet)
+        .accounts({ platformConfig, admin: userA })
+        .signers([userAKeypair])
+        .rpc();
+      await program.methods
+        .acceptAdmin()
+        .accounts({ platformConfig, pendingAdmin: platformWallet })
+        .signers([wallet.payer])
+        .rpc();
+      platformConfigAccount = await program.account.platformConfig.fetch(
+        platformConfig
+      );
+      assert.equal(
+        platformConfigAccount.admin.toBase58(),
+        platformWallet.toBase58(),
+        "admin is wrong"
+      );
+    });
+  });
+
+  it("Initialize Presale - supply mismatch - fail", async () => {
+    try {
+      await program.methods
+        .initializePresale({
+          name: configData.name,
+          symbol: configData.symbol,
+          uri: configData.uri,
+          decimals: configData.decimals,
+          startTimestamp: configData.startTime,
+          duration: configData.duration,
+          totalSupply: configData.totalSupply,
+          roundAmounts: configData.roundAmounts,
+          roundPrices: configData.roundPrices,
+          roundEndTimes: getRoundEndTimes(),
+          roundPricingModes: [],
+          purchaseLimits: configData.purchaseLimits,
+          roundPurchaseLimits: [],
+          roundMerkleRoots: [],
+          vesting: configData.vesting,
+          roundVestings: [],
+          referral: configData.referral,
+          oracle: null,
+          isProRata: false,
+          softCap: configData.softCap,
+          hardCap: configData.hardCap,
+          unsoldAction: { burn: {} },
+          selloutCooldown: configData.selloutCooldown,
+          liquidityAmount: configData.liquidityAmount.addn(1),
+          feePercent: configData.feePercent,
+          liquidityPercent: configData.liquidityPercent,
+        })
+        .accounts({
+          platformConfig,
+          platformWallet,
+          presaleConfig,
+          creator: admin,
+          baseMint,
+          newMint,
+          feeMint,
+          creatorFeeTokenAccount: adminFeeTokenAccount,
+          platformFeeTokenAccount: getPlatformFeeTokenAccount(),
+          vaultBaseTokenAccount,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          tokenProgram2022: TOKEN_2022_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([adminKeypair])
+        .rpc();
+    } catch (err) {
+      assert.equal(err.error.errorMessage, "Invalid supply");
+    }
+  });
+
+  it("Initialize Presale - empty symbol - fail", async () => {
+    try {
+      await program.methods
+        .initializePresale({
+          name: configData.name,
+          symbol: "",
+          uri: configData.uri,
+          decimals: configData.decimals,
+          startTimestamp: configData.startTime,
+          duration: configData.duration,
+          totalSupply: configData.totalSupply,
+          roundAmounts: configData.roundAmounts,
+          roundPrices: configData.roundPrices,
+          roundEndTimes: getRoundEndTimes(),
+          roundPricingModes: [],
+          purchaseLimits: configData.purchaseLimits,
+          roundPurchaseLimits: [],
+          roundMerkleRoots: [],
+          vesting: configData.vesting,
+          roundVestings: [],
+          referral: configData.referral,
+          oracle: null,
+          isProRata: false,
+          softCap: configData.softCap,
+          hardCap: configData.hardCap,
+          unsoldAction: { burn: {} },
+          selloutCooldown: configData.selloutCooldown,
+          liquidityAmount: configData.liquidityAmount,
+          feePercent: configData.feePercent,
+          liquidityPercent: configData.liquidityPercent,
+        })
+        .accounts({
+          platformConfig,
+          platformWallet,
+          presaleConfig,
+          creator: admin,
+          baseMint,
+          newMint,
+          feeMint,
+          creatorFeeTokenAccount: adminFeeTokenAccount,
+          platformFeeTokenAccount: getPlatformFeeTokenAccount(),
+          vaultBaseTokenAccount,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          tokenProgram2022: TOKEN_2022_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([adminKeypair])
+        .rpc();
+    } catch (err) {
+      assert.equal(err.error.errorMessage, "Invalid token metadata");
+    }
+  });
+
+  it("Initialize Presale - curve end price below start - fail", async () => {
+    try {
+      await program.methods
+        .initializePresale({
+          name: configData.name,
+          symbol: configData.symbol,
+          uri: configData.uri,
+          decimals: configData.decimals,
+          startTimestamp: configData.startTime,
+          duration: configData.duration,
+          totalSupply: configData.totalSupply,
+          roundAmounts: configData.roundAmounts,
+          roundPrices: configData.roundPrices,
+          roundEndTimes: getRoundEndTimes(),
+          roundPricingModes: configData.roundPrices.map((price) => ({
+            linear: { endPrice: price.subn(1) },
+          })),
+          purchaseLimits: configData.purchaseLimits,
+          roundPurchaseLimits: [],
+          roundMerkleRoots: [],
+          vesting: configData.vesting,
+          roundVestings: [],
+          referral: configData.referral,
+          oracle: null,
+          isProRata: false,
+          softCap: configData.softCap,
+          hardCap: configData.hardCap,
+          unsoldAction: { burn: {} },
+          selloutCooldown: configData.selloutCooldown,
+          liquidityAmount: configData.liquidityAmount,
+          feePercent: configData.feePercent,
+          liquidityPercent: configData.liquidityPercent,
+        })
+        .accounts({
+          platformConfig,
+          platformWallet,
+          presaleConfig,
+          creator: admin,
+          baseMint,
+          newMint,
+          feeMint,
+          creatorFeeTokenAccount: adminFeeTokenAccount,
+          platformFeeTokenAccount: getPlatformFeeTokenAccount(),
+          vaultBaseTokenAccount,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          tokenProgram2022: TOKEN_2022_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([adminKeypair])
+        .rpc();
+    } catch (err) {
+      assert.equal(err.error.errorMessage, "Invalid pricing mode");
+    }
+  });
+
+  it("Initialize Presale - pro-rata with referral rewards - fail", async () => {
+    try {
+      await program.methods
+        .initializePresale({
+          name: configData.name,
+          symbol: configData.symbol,
+          uri: configData.uri,
+          decimals: configData.decimals,
+          startTimestamp: configData.startTime,
+          duration: configData.duration,
+          totalSupply: configData.totalSupply,
+          roundAmounts: configData.roundAmounts,
+          roundPrices: configData.roundPrices,
+          roundEndTimes: getRoundEndTimes(),
+          roundPricingModes: [],
+          purchaseLimits: configData.purchaseLimits,
+          roundPurchaseLimits: [],
+          roundMerkleRoots: [],
+          vesting: configData.vesting,
+          roundVestings: [],
+          referral: configData.referral,
+          oracle: null,
+          isProRata: true,
+          softCap: configData.softCap,
+          hardCap: configData.hardCap,
+          unsoldAction: { burn: {} },
+          selloutCooldown: configData.selloutCooldown,
+          liquidityAmount: configData.liquidityAmount,
+          feePercent: configData.feePercent,
+          liquidityPercent: configData.liquidityPercent,
+        })
+        .accounts({
+          platformConfig,
+          platformWallet,
+          presaleConfig,
+          creator: admin,
+          baseMint,
+          newMint,
+          feeMint,
+          creatorFeeTokenAccount: adminFeeTokenAccount,
+          platformFeeTokenAccount: getPlatformFeeTokenAccount(),
+          vaultBaseTokenAccount,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          tokenProgram2022: TOKEN_2022_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([adminKeypair])
+        .rpc();
+    } catch (err) {
+      assert.equal(err.error.errorMessage, "Not supported in pro-rata mode");
+    }
+  });
+
   it("Initialize Presale!", async () => {
+    // round 1 is limited to userA and userB
+    allowlist = buildAllowlist([{ wallet: userA }, { wallet: userB }]);
     configData.startTime = new anchor.BN(Math.floor(Date.now() / 1000) + 10); // 10 seconds from now
 
     const txSig = await program.methods
-      .initializePresale(
-        configData.decimals,
-        configData.startTime,
-        configData.duration,
-        configData.roundPrices,
-        configData.feePercent
-      )
+      .initializePresale({
+        name: configData.name,
+        symbol: configData.symbol,
+        uri: configData.uri,
+        decimals: configData.decimals,
+        startTimestamp: configData.startTime,
+        duration: configData.duration,
+        totalSupply: configData.totalSupply,
+        roundAmounts: configData.roundAmounts,
+        roundPrices: configData.roundPrices,
+        roundEndTimes: getRoundEndTimes(),
+        roundPricingModes: [],
+        purchaseLimits: configData.purchaseLimits,
+        roundPurchaseLimits: [],
+        roundMerkleRoots: [allowlist.root, null, null, null],
+        vesting: configData.vesting,
+        roundVestings: [],
+        referral: configData.referral,
+        oracle: null,
+        isProRata: false,
+        softCap: configData.softCap,
+        hardCap: configData.hardCap,
+        unsoldAction: { burn: {} },
+        selloutCooldown: configData.selloutCooldown,
+        liquidityAmount: configData.liquidityAmount,
+        feePercent: configData.feePercent,
+        liquidityPercent: configData.liquidityPercent,
+      })
       .accounts({
         platformConfig,
         platformWallet,
@@ -297,9 +728,10 @@ describe("presale-token", () => {
         newMint,
         feeMint,
         creatorFeeTokenAccount: adminFeeTokenAccount,
-        platformFeeTokenAccount,
+        platformFeeTokenAccount: getPlatformFeeTokenAccount(),
         vaultBaseTokenAccount,
         tokenProgram: TOKEN_PROGRAM_ID,
+        tokenProgram2022: TOKEN_2022_PROGRAM_ID,
         systemProgram: SystemProgram.programId,
       })
       .signers([adminKeypair])
@@ -315,6 +747,35 @@ describe("presale-token", () => {
       2_000_000,
       "feeAmountNormal is wrong"
     );
+    const initialized = (await getCpiEvents(program, txSig)).find(
+      (event) => event.name === "presaleInitialized"
+    );
+    assert.equal(
+      initialized.data.platformFeeAmount.toNumber(),
+      platformConfigAccount.feeAmountNormal.toNumber(),
+      "platformFeeAmount is wrong"
+    );
+
+    // the presale creates the mint and holds its mint authority
+    const newMintAccount = await getMint(
+      connection,
+      newMint,
+      undefined,
+      TOKEN_2022_PROGRAM_ID
+    );
+    assert.equal(
+      newMintAccount.mintAuthority.toBase58(),
+      presaleConfig.toBase58(),
+      "mintAuthority is wrong"
+    );
+    const tokenMetadata = await getTokenMetadata(connection, newMint);
+    assert.equal(tokenMetadata.symbol, configData.symbol, "symbol is wrong");
+    // the creator cannot rename the symbol the platform fee was charged on
+    assert.equal(
+      tokenMetadata.updateAuthority.toBase58(),
+      presaleConfig.toBase58(),
+      "updateAuthority is wrong"
+    );
 
     // fetch presale config
     const presaleConfigAccount = await program.account.presaleConfig.fetch(
@@ -350,14 +811,118 @@ describe("presale-token", () => {
       false,
       "isFinalized is wrong"
     );
+    assert.equal(
+      presaleConfigAccount.presaleId.toString(),
+      "0",
+      "presaleId is wrong"
+    );
+    const creatorCounterAccount = await program.account.creatorCounter.fetch(
+      pda([CREATOR_COUNTER_TAG, admin.toBuffer()], programId)
+    );
+    assert.equal(
+      creatorCounterAccount.presaleCount.toString(),
+      "1",
+      "presaleCount is wrong"
+    );
+    assert.equal(
+      presaleConfigAccount.softCapAmount.toString(),
+      configData.softCap.mul(PRECISION).toString(),
+      "softCapAmount is wrong"
+    );
+    assert.equal(
+      presaleConfigAccount.hardCapAmount.toString(),
+      configData.hardCap.mul(PRECISION).toString(),
+      "hardCapAmount is wrong"
+    );
+    assert.equal(
+      presaleConfigAccount.liquidityAmount.toString(),
+      configData.liquidityAmount.mul(PRECISION).toString(),
+      "liquidityAmount is wrong"
+    );
+    assert.equal(
+      presaleConfigAccount.roundConfigs[3].roundEndTime.toNumber(),
+      configData.startTime.addn(40).toNumber(),
+      "roundEndTime is wrong"
+    );
+  });
+
+  const getVaultPaymentTokenAccount = () =>
+    pda(
+      [
+        VAULT_BASE_TOKEN_ACCOUNT_TAG,
+        presaleConfig.toBuffer(),
+        paymentMint.toBuffer(),
+      ],
+      programId
+    );
+
+  it("Add payment mint - success", async () => {
+    paymentMint = await createMint(
+      connection,
+      wallet.payer,
+      wallet.publicKey,
+      null,
+      6,
+      Keypair.generate(),
+      undefined,
+      TOKEN_PROGRAM_ID
+    );
+    const userBPaymentTokenAccount = await getOrCreateAssociatedTokenAccount(
+      connection,
+      wallet.payer,
+      paymentMint,
+      userB
+    );
+    await mintTo(
+      connection,
+      wallet.payer,
+      paymentMint,
+      userBPaymentTokenAccount.address,
+      wallet.publicKey,
+      BigInt(new anchor.BN(1_000_000_000).muln(1_000_000).toString())
+    );
+
+    // 1 whole base token buys with 1 whole payment token
+    const rate = new anchor.BN(1_000_000);
+    await program.methods
+      .addPaymentMint(rate)
+      .accounts({
+        presaleConfig,
+        creator: admin,
+        paymentMint,
+        vaultPaymentTokenAccount: getVaultPaymentTokenAccount(),
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([adminKeypair])
+      .rpc();
+
+    const presaleConfigAccount = await program.account.presaleConfig.fetch(
+      presaleConfig
+    );
+    assert.equal(
+      presaleConfigAccount.paymentMints[0].mint.toBase58(),
+      paymentMint.toBase58(),
+      "payment mint is wrong"
+    );
+    assert.equal(
+      presaleConfigAccount.paymentMints[0].rate.toString(),
+      rate.toString(),
+      "payment rate is wrong"
+    );
   });
 
   describe("Buy Pretoken", () => {
     it("Round 1 - userA buy before start - fail", async () => {
       try {
         const txSig = await program.methods
-          .buyPretoken(0, new anchor.BN(1000).mul(PRECISION))
+          .buyPretoken(
+            0,
+            new anchor.BN(1000).mul(PRECISION),
+            allowlist.getProof(userA)
+          )
           .accounts({
+            platformConfig,
             presaleConfig,
             user: userA,
             userAllocation: getUserAllocation(userA),
@@ -378,8 +943,9 @@ describe("presale-token", () => {
     it("Round 1 - userA buy with wrong round - fail", async () => {
       try {
         const txSig = await program.methods
-          .buyPretoken(1, new anchor.BN(1000).mul(PRECISION))
+          .buyPretoken(1, new anchor.BN(1000).mul(PRECISION), null)
           .accounts({
+            platformConfig,
             presaleConfig,
             user: userA,
             userAllocation: getUserAllocation(userA),
@@ -413,8 +979,13 @@ describe("presale-token", () => {
 
       const userAAllocation = getUserAllocation(userA);
       const txSig = await program.methods
-        .buyPretoken(0, new anchor.BN(100_000_000).mul(PRECISION))
+        .buyPretoken(
+          0,
+          new anchor.BN(100_000_000).mul(PRECISION),
+          allowlist.getProof(userA)
+        )
         .accounts({
+          platformConfig,
           presaleConfig,
           user: userA,
           userAllocation: userAAllocation,
@@ -425,7 +996,7 @@ describe("presale-token", () => {
           systemProgram: SystemProgram.programId,
         })
         .signers([userAKeypair])
-        .rpc();
+        .rpc({ commitment: "confirmed" });
       console.log(`Transaction Signature: ${txSig}`);
       const userAAllocationAccount = await program.account.userAllocation.fetch(
         userAAllocation
@@ -435,32 +1006,128 @@ describe("presale-token", () => {
         new anchor.BN(100_000_000).mul(PRECISION).toString(),
         "amount is wrong"
       );
-    });
 
-    it("Round 1 - userB buy try 150M but receive 145M - success", async () => {
-      const userBAllocation = getUserAllocation(userB);
-      const txSig = await program.methods
-        .buyPretoken(0, new anchor.BN(150_000_000).mul(PRECISION))
-        .accounts({
-          presaleConfig,
-          user: userB,
-          userAllocation: userBAllocation,
-          baseMint,
-          vaultBaseTokenAccount,
-          userBaseTokenAccount: userBBaseTokenAccount,
-          tokenProgram: TOKEN_PROGRAM_ID,
-          systemProgram: SystemProgram.programId,
-        })
-        .signers([userBKeypair])
-        .rpc();
-      console.log(`Transaction Signature: ${txSig}`);
-      const userBAllocationAccount = await program.account.userAllocation.fetch(
-        userBAllocation
+      const events = await getCpiEvents(program, txSig);
+      const bought = events.find((event) => event.name === "pretokenBought");
+      assert.equal(
+        bought.data.pretokenAmount.toString(),
+        new anchor.BN(100_000_000).mul(PRECISION).toString(),
+        "event pretokenAmount is wrong"
       );
       assert.equal(
-        userBAllocationAccount.amounts[0].toString(),
-        new anchor.BN(145_000_000).mul(PRECISION).toString(),
-        "amount is wrong"
+        userAAllocationAccount.baseAmount.toString(),
+        new anchor.BN(100_000_000)
+          .mul(PRECISION)
+          .mul(configData.roundPrices[0])
+          .div(PRECISION)
+          .toString(),
+        "baseAmount is wrong"
+      );
+    });
+
+    it("Round 1 - userA buy below minimum - fail", async () => {
+      try {
+        await program.methods
+          .buyPretoken(
+            0,
+            new anchor.BN(1).mul(PRECISION),
+            allowlist.getProof(userA)
+          )
+          .accounts({
+            platformConfig,
+            presaleConfig,
+            user: userA,
+            userAllocation: getUserAllocation(userA),
+            baseMint,
+            vaultBaseTokenAccount,
+            userBaseTokenAccount: userABaseTokenAccount,
+            tokenProgram: TOKEN_PROGRAM_ID,
+            systemProgram: SystemProgram.programId,
+          })
+          .signers([userAKeypair])
+          .rpc();
+      } catch (err) {
+        assert.equal(err.error.errorMessage, "Purchase below minimum");
+      }
+    });
+
+    it("Add payment mint - after start - fail", async () => {
+      try {
+        await program.methods
+          .addPaymentMint(new anchor.BN(1_000_000))
+          .accounts({
+            presaleConfig,
+            creator: admin,
+            paymentMint: feeMint,
+            vaultPaymentTokenAccount: pda(
+              [
+                VAULT_BASE_TOKEN_ACCOUNT_TAG,
+                presaleConfig.toBuffer(),
+                feeMint.toBuffer(),
+              ],
+              programId
+            ),
+            tokenProgram: TOKEN_PROGRAM_ID,
+            systemProgram: SystemProgram.programId,
+          })
+          .signers([adminKeypair])
+          .rpc();
+      } catch (err) {
+        assert.equal(err.error.errorMessage, "Presale already started");
+      }
+    });
+
+    it("Round 1 - userC buy without allowlist - fail", async () => {
+      try {
+        await program.methods
+          .buyPretoken(0, new anchor.BN(1000).mul(PRECISION), null)
+          .accounts({
+            platformConfig,
+            presaleConfig,
+            user: userC,
+            userAllocation: getUserAllocation(userC),
+            baseMint,
+            vaultBaseTokenAccount,
+            userBaseTokenAccount: userCBaseTokenAccount,
+            tokenProgram: TOKEN_PROGRAM_ID,
+            systemProgram: SystemProgram.programId,
+          })
+          .signers([userCKeypair])
+          .rpc();
+      } catch (err) {
+        assert.equal(err.error.errorMessage, "Wallet not allowlisted");
+      }
+    });
+
+    it("Round 1 - userB buy try 150M but receive 145M - success", async () => {
+      const userBAllocation = getUserAllocation(userB);
+      const txSig = await program.methods
+        .buyPretoken(
+          0,
+          new anchor.BN(150_000_000).mul(PRECISION),
+          allowlist.getProof(userB)
+        )
+        .accounts({
+          platformConfig,
+          presaleConfig,
+          user: userB,
+          userAllocation: userBAllocation,
+          baseMint,
+          vaultBaseTokenAccount,
+          userBaseTokenAccount: userBBaseTokenAccount,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([userBKeypair])
+        .rpc();
+      console.log(`Transaction Signature: ${txSig}`);
+      const userBAllocationAccount = await program.account.userAllocation.fetch(
+        userBAllocation
+      );
+      assert.equal(
+        userBAllocationAccount.amounts[0].toString(),
+        new anchor.BN(145_000_000).mul(PRECISION).toString(),
+        "amount is wrong"
       );
     });
 
@@ -478,8 +1145,9 @@ describe("presale-token", () => {
       const userAAllocationAccountBefore =
         await program.account.userAllocation.fetch(userAAllocation);
       const txSig = await program.methods
-        .buyPretoken(1, new anchor.BN(235_000_000).mul(PRECISION))
+        .buyPretoken(1, new anchor.BN(235_000_000).mul(PRECISION), null)
         .accounts({
+          platformConfig,
           presaleConfig,
           user: userA,
           userAllocation: userAAllocation,
@@ -517,8 +1185,9 @@ describe("presale-token", () => {
       const userAAllocationAccountBefore =
         await program.account.userAllocation.fetch(userAAllocation);
       const txSig = await program.methods
-        .buyPretoken(2, new anchor.BN(215_000_000).mul(PRECISION))
+        .buyPretoken(2, new anchor.BN(215_000_000).mul(PRECISION), null)
         .accounts({
+          platformConfig,
           presaleConfig,
           user: userA,
           userAllocation: userAAllocation,
@@ -552,18 +1221,23 @@ describe("presale-token", () => {
         "currentRound is wrong"
       );
 
+      // userB pays this round with the payment mint
       const userBAllocation = getUserAllocation(userB);
       const userBAllocationAccountBefore =
         await program.account.userAllocation.fetch(userBAllocation);
       const txSig = await program.methods
-        .buyPretoken(3, new anchor.BN(50_000_000).mul(PRECISION))
+        .buyPretoken(3, new anchor.BN(50_000_000).mul(PRECISION), null)
         .accounts({
+          platformConfig,
           presaleConfig,
           user: userB,
           userAllocation: userBAllocation,
-          baseMint,
-          vaultBaseTokenAccount,
-          userBaseTokenAccount: userBBaseTokenAccount,
+          baseMint: paymentMint,
+          vaultBaseTokenAccount: getVaultPaymentTokenAccount(),
+          userBaseTokenAccount: getAssociatedTokenAddressSync(
+            paymentMint,
+            userB
+          ),
           tokenProgram: TOKEN_PROGRAM_ID,
           systemProgram: SystemProgram.programId,
         })
@@ -579,6 +1253,69 @@ describe("presale-token", () => {
         new anchor.BN(50_000_000).mul(PRECISION).toString(),
         "amount is wrong"
       );
+
+      const events = await getCpiEvents(program, txSig);
+      const bought = events.find((event) => event.name === "pretokenBought");
+      assert.equal(
+        userBAllocationAccountAfter.paymentAmounts[0].toString(),
+        bought.data.baseAmount.toString(),
+        "paymentAmounts is wrong"
+      );
+      const vaultPaymentTokenAccount = await getAccount(
+        connection,
+        getVaultPaymentTokenAccount(),
+        "processed"
+      );
+      assert.equal(
+        vaultPaymentTokenAccount.amount.toString(),
+        bought.data.paymentAmount.toString(),
+        "payment vault balance is wrong"
+      );
+    });
+
+    it("Referral - userA register referrer - success", async () => {
+      await program.methods
+        .registerReferrer()
+        .accounts({
+          presaleConfig,
+          referrer: userA,
+          referrerStats: getReferrerStats(userA),
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([userAKeypair])
+        .rpc();
+
+      const referrerStats = await program.account.referrerStats.fetch(
+        getReferrerStats(userA)
+      );
+      assert.equal(
+        referrerStats.referrer.toString(),
+        userA.toString(),
+        "referrer is wrong"
+      );
+    });
+
+    it("Round 4 - userA buy with self referral - fail", async () => {
+      try {
+        await program.methods
+          .buyPretoken(3, new anchor.BN(1_000).mul(PRECISION), null)
+          .accounts({
+            platformConfig,
+            presaleConfig,
+            user: userA,
+            userAllocation: getUserAllocation(userA),
+            baseMint,
+            vaultBaseTokenAccount,
+            userBaseTokenAccount: userABaseTokenAccount,
+            referrerStats: getReferrerStats(userA),
+            tokenProgram: TOKEN_PROGRAM_ID,
+            systemProgram: SystemProgram.programId,
+          })
+          .signers([userAKeypair])
+          .rpc();
+      } catch (err) {
+        assert.equal(err.error.errorMessage, "Self referral not allowed");
+      }
     });
 
     it("Round 4 - userC buy 55M - success", async () => {
@@ -593,14 +1330,16 @@ describe("presale-token", () => {
 
       const userCAllocation = getUserAllocation(userC);
       const txSig = await program.methods
-        .buyPretoken(3, new anchor.BN(55_000_000).mul(PRECISION))
+        .buyPretoken(3, new anchor.BN(55_000_000).mul(PRECISION), null)
         .accounts({
+          platformConfig,
           presaleConfig,
           user: userC,
           userAllocation: userCAllocation,
           baseMint,
           vaultBaseTokenAccount,
           userBaseTokenAccount: userCBaseTokenAccount,
+          referrerStats: getReferrerStats(userA),
           tokenProgram: TOKEN_PROGRAM_ID,
           systemProgram: SystemProgram.programId,
         })
@@ -615,6 +1354,36 @@ describe("presale-token", () => {
         new anchor.BN(55_000_000).mul(PRECISION).toString(),
         "amount is wrong"
       );
+      assert.equal(
+        userCAllocationAccount.referrer.toString(),
+        userA.toString(),
+        "referrer is wrong"
+      );
+
+      const referrerStats = await program.account.referrerStats.fetch(
+        getReferrerStats(userA)
+      );
+      assert.equal(
+        referrerStats.referralCount.toString(),
+        "1",
+        "referralCount is wrong"
+      );
+      assert.equal(
+        referrerStats.referredAmount.toString(),
+        new anchor.BN(55_000_000).mul(PRECISION).toString(),
+        "referredAmount is wrong"
+      );
+      assert.isTrue(
+        referrerStats.baseRewardAmount.gtn(0),
+        "baseRewardAmount is wrong"
+      );
+      // the presale sold out to its hard cap
+      const presaleConfigAccountAfter =
+        await program.account.presaleConfig.fetch(presaleConfig);
+      assert.isTrue(
+        presaleConfigAccountAfter.selloutTimestamp.gtn(0),
+        "selloutTimestamp is wrong"
+      );
     });
   });
   describe("Finalize", () => {
@@ -623,13 +1392,25 @@ describe("presale-token", () => {
         const txSig = await program.methods
           .finalizePresale()
           .accounts({
+            platformConfig,
             presaleConfig,
             creator: admin,
             newMint,
             baseMint,
             vaultBaseTokenAccount,
+            liquidityAuthority,
+            liquidityTokenAccount,
+            liquidityBaseTokenAccount,
+            creatorTokenAccount: null,
+            ammPool: getAmmPool(),
+            ammVaultNewTokenAccount: getAmmVault(newMint),
+            ammVaultBaseTokenAccount: getAmmVault(baseMint),
+            ammLpMint: getAmmLpMint(),
+            lpTokenAccount: getLpTokenAccount(),
+            ammProgram: ammProgram.programId,
             tokenProgram: TOKEN_PROGRAM_ID,
             tokenProgram2022: TOKEN_2022_PROGRAM_ID,
+            associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
             systemProgram: SystemProgram.programId,
           })
           .signers([adminKeypair])
@@ -657,18 +1438,271 @@ describe("presale-token", () => {
       const txSig = await program.methods
         .finalizePresale()
         .accounts({
+          platformConfig,
           presaleConfig,
           creator: admin,
           newMint,
           baseMint,
           vaultBaseTokenAccount,
+          liquidityAuthority,
+          liquidityTokenAccount,
+          liquidityBaseTokenAccount,
+          creatorTokenAccount: null,
+          ammPool: getAmmPool(),
+          ammVaultNewTokenAccount: getAmmVault(newMint),
+          ammVaultBaseTokenAccount: getAmmVault(baseMint),
+          ammLpMint: getAmmLpMint(),
+          lpTokenAccount: getLpTokenAccount(),
+          ammProgram: ammProgram.programId,
           tokenProgram: TOKEN_PROGRAM_ID,
           tokenProgram2022: TOKEN_2022_PROGRAM_ID,
+          associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
           systemProgram: SystemProgram.programId,
         })
         .signers([adminKeypair])
         .rpc();
       console.log(`Transaction Signature: ${txSig}`);
+
+      const presaleConfigAccountAfter =
+        await program.account.presaleConfig.fetch(presaleConfig);
+      assert.equal(
+        presaleConfigAccountAfter.isSuccess,
+        true,
+        "isSuccess is wrong"
+      );
+      assert.equal(
+        presaleConfigAccountAfter.unsoldAmount.toString(),
+        "0",
+        "unsoldAmount is wrong"
+      );
+
+      const pool = await ammProgram.account.pool.fetch(getAmmPool());
+      assert.equal(
+        pool.reserveA.toString(),
+        presaleConfigAccountAfter.liquidityAmount.toString(),
+        "pool new token reserve is wrong"
+      );
+      assert.equal(
+        pool.reserveB.toString(),
+        presaleConfigAccountAfter.liquidityBaseAmount.toString(),
+        "pool base token reserve is wrong"
+      );
+
+      // the liquidity authority deposited everything it was given and
+      // holds the LP tokens
+      const liquidityBaseAccount = await getAccount(
+        connection,
+        liquidityBaseTokenAccount,
+        "processed"
+      );
+      assert.equal(
+        liquidityBaseAccount.amount.toString(),
+        "0",
+        "liquidity base balance is wrong"
+      );
+      const lpAccount = await getAccount(
+        connection,
+        getLpTokenAccount(),
+        "processed"
+      );
+      assert.equal(
+        lpAccount.owner.toBase58(),
+        liquidityAuthority.toBase58(),
+        "lp token owner is wrong"
+      );
+      assert.isTrue(lpAccount.amount > BigInt(0), "lp amount is wrong");
+    });
+
+    it("Withdraw - creator withdraw from a stray vault - fail", async () => {
+      // anyone can open a baseMint account owned by the presale
+      const strayVault = await getOrCreateAssociatedTokenAccount(
+        connection,
+        wallet.payer,
+        baseMint,
+        presaleConfig,
+        true
+      );
+      try {
+        await program.methods
+          .withdrawProceeds(new anchor.BN(1))
+          .accounts({
+            platformConfig,
+            platformWallet,
+            presaleConfig,
+            creator: admin,
+            baseMint,
+            vaultBaseTokenAccount: strayVault.address,
+            creatorBaseTokenAccount: adminBaseTokenAccount,
+            platformFeeTokenAccount: null,
+            tokenProgram: TOKEN_PROGRAM_ID,
+          })
+          .signers([adminKeypair])
+          .rpc();
+        assert.fail("withdraw from a stray vault should fail");
+      } catch (err) {
+        assert.equal(err.error.errorMessage, "Invalid vault");
+      }
+    });
+
+    it("Withdraw - creator withdraw proceeds - success", async () => {
+      const platformBaseTokenAccount = await getOrCreateAssociatedTokenAccount(
+        connection,
+        wallet.payer,
+        baseMint,
+        platformWallet,
+        false,
+        "processed",
+        undefined,
+        TOKEN_PROGRAM_ID
+      );
+      const presaleConfigAccount = await program.account.presaleConfig.fetch(
+        presaleConfig
+      );
+      const creatorBalanceBefore = (
+        await getAccount(connection, adminBaseTokenAccount, "processed")
+      ).amount;
+
+      const txSig = await program.methods
+        .withdrawProceeds(presaleConfigAccount.proceedsAmount)
+        .accounts({
+          platformConfig,
+          platformWallet,
+          presaleConfig,
+          creator: admin,
+          baseMint,
+          vaultBaseTokenAccount,
+          creatorBaseTokenAccount: adminBaseTokenAccount,
+          platformFeeTokenAccount: platformBaseTokenAccount.address,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .signers([adminKeypair])
+        .rpc();
+      console.log(`Transaction Signature: ${txSig}`);
+
+      const presaleConfigAccountAfter =
+        await program.account.presaleConfig.fetch(presaleConfig);
+      assert.equal(
+        presaleConfigAccountAfter.withdrawnAmount.toString(),
+        presaleConfigAccount.proceedsAmount.toString(),
+        "withdrawnAmount is wrong"
+      );
+      const creatorBalanceAfter = (
+        await getAccount(connection, adminBaseTokenAccount, "processed")
+      ).amount;
+      assert.isTrue(
+        creatorBalanceAfter > creatorBalanceBefore,
+        "creator balance is wrong"
+      );
+    });
+
+    it("Withdraw - creator withdraw payment mint proceeds - success", async () => {
+      const [creatorPaymentTokenAccount, platformPaymentTokenAccount] =
+        await Promise.all(
+          [admin, platformWallet].map((owner) =>
+            getOrCreateAssociatedTokenAccount(
+              connection,
+              wallet.payer,
+              paymentMint,
+              owner
+            )
+          )
+        );
+      const presaleConfigAccount = await program.account.presaleConfig.fetch(
+        presaleConfig
+      );
+      const vaultAmount = presaleConfigAccount.paymentMints[0].vaultAmount;
+
+      await program.methods
+        .withdrawProceeds(vaultAmount)
+        .accounts({
+          platformConfig,
+          platformWallet,
+          presaleConfig,
+          creator: admin,
+          baseMint: paymentMint,
+          vaultBaseTokenAccount: getVaultPaymentTokenAccount(),
+          creatorBaseTokenAccount: creatorPaymentTokenAccount.address,
+          platformFeeTokenAccount: platformPaymentTokenAccount.address,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .signers([adminKeypair])
+        .rpc();
+
+      const vaultPaymentTokenAccount = await getAccount(
+        connection,
+        getVaultPaymentTokenAccount(),
+        "processed"
+      );
+      assert.equal(
+        vaultPaymentTokenAccount.amount.toString(),
+        "0",
+        "payment vault balance is wrong"
+      );
+    });
+
+    it("Withdraw - creator withdraw twice - fail", async () => {
+      try {
+        await program.methods
+          .withdrawProceeds(new anchor.BN(1))
+          .accounts({
+            platformConfig,
+            platformWallet,
+            presaleConfig,
+            creator: admin,
+            baseMint,
+            vaultBaseTokenAccount,
+            creatorBaseTokenAccount: adminBaseTokenAccount,
+            platformFeeTokenAccount: getAssociatedTokenAddressSync(
+              baseMint,
+              platformWallet
+            ),
+            tokenProgram: TOKEN_PROGRAM_ID,
+          })
+          .signers([adminKeypair])
+          .rpc();
+      } catch (err) {
+        assert.equal(err.error.errorMessage, "Insufficient proceeds");
+      }
+    });
+
+    it("Referral - userA claim referral rewards - success", async () => {
+      const referrerStats = await program.account.referrerStats.fetch(
+        getReferrerStats(userA)
+      );
+      const userABalanceBefore = (
+        await getAccount(connection, userABaseTokenAccount, "processed")
+      ).amount;
+
+      const txSig = await program.methods
+        .claimReferralRewards()
+        .accounts({
+          presaleConfig,
+          referrer: userA,
+          referrerStats: getReferrerStats(userA),
+          newMint,
+          referrerTokenAccount: pda(
+            [USER_TOKEN_ACCOUNT_TAG, presaleConfig.toBuffer(), userA.toBuffer()],
+            programId
+          ),
+          baseMint,
+          vaultBaseTokenAccount,
+          referrerBaseTokenAccount: userABaseTokenAccount,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          tokenProgram2022: TOKEN_2022_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([userAKeypair])
+        .rpc();
+      console.log(`Transaction Signature: ${txSig}`);
+
+      const userABalanceAfter = (
+        await getAccount(connection, userABaseTokenAccount, "processed")
+      ).amount;
+      assert.equal(
+        (userABalanceAfter - userABalanceBefore).toString(),
+        referrerStats.baseRewardAmount.toString(),
+        "referral reward is wrong"
+      );
     });
 
     it("Claim - userA claim - success", async () => {
@@ -704,6 +1738,2133 @@ describe("presale-token", () => {
         )
       ).amount;
       console.log(userABalance.toString());
+
+      const allocation = await program.account.userAllocation.fetch(
+        userAAllocation
+      );
+      assert.equal(
+        allocation.claimedAmount.toString(),
+        userABalance.toString(),
+        "claimedAmount is wrong"
+      );
+    });
+
+    it("Claim - userA claim twice - fail", async () => {
+      const userATokenAccount = pda(
+        [USER_TOKEN_ACCOUNT_TAG, presaleConfig.toBuffer(), userA.toBuffer()],
+        programId
+      );
+      try {
+        await program.methods
+          .claimOrRefund()
+          .accounts({
+            presaleConfig,
+            user: userA,
+            userAllocation: getUserAllocation(userA),
+            newMint,
+            userTokenAccount: userATokenAccount,
+            baseMint,
+            vaultBaseTokenAccount,
+            userBaseTokenAccount: userABaseTokenAccount,
+            tokenProgram: TOKEN_PROGRAM_ID,
+            tokenProgram2022: TOKEN_2022_PROGRAM_ID,
+            systemProgram: SystemProgram.programId,
+          })
+          .signers([userAKeypair])
+          .rpc();
+      } catch (err) {
+        assert.equal(err.error.errorMessage, "Nothing to claim");
+      }
+    });
+
+    it("Close - creator close presale with open allocations - fail", async () => {
+      try {
+        await program.methods
+          .closePresale()
+          .accounts({
+            presaleConfig,
+            creator: admin,
+            baseMint,
+            vaultBaseTokenAccount,
+            creatorBaseTokenAccount: null,
+            tokenProgram: TOKEN_PROGRAM_ID,
+            systemProgram: SystemProgram.programId,
+          })
+          .signers([adminKeypair])
+          .rpc();
+      } catch (err) {
+        assert.equal(err.error.errorMessage, "Presale not settled");
+      }
+    });
+  });
+
+  describe("Native SOL", () => {
+    // userB runs a single round presale paid in SOL and priced in USD
+    const nativePresaleConfig = pda(
+      [PRESALE_CONFIG_TAG, userB.toBuffer(), presaleIdSeed(0)],
+      programId
+    );
+    const nativeVaultSol = pda(
+      [VAULT_SOL_TAG, nativePresaleConfig.toBuffer()],
+      programId
+    );
+    const nativeVaultBaseTokenAccount = pda(
+      [VAULT_BASE_TOKEN_ACCOUNT_TAG, nativePresaleConfig.toBuffer()],
+      programId
+    );
+    const priceFeed = pda(
+      [PRICE_FEED_TAG, wallet.publicKey.toBuffer()],
+      oracleProgram.programId
+    );
+    const nativeNewMint = pda(
+      [NEW_MINT_TAG, nativePresaleConfig.toBuffer()],
+      programId
+    );
+
+    // SOL at $150.00000000 +- $0.10
+    const setSolPrice = (publishTime: number) =>
+      oracleProgram.methods
+        .setPrice(
+          new anchor.BN(15_000_000_000),
+          new anchor.BN(10_000_000),
+          -8,
+          new anchor.BN(publishTime)
+        )
+        .accounts({
+          priceFeed,
+          authority: wallet.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc();
+
+    it("Initialize Presale - native SOL - success", async () => {
+      const userBFeeTokenAccount = await getOrCreateAssociatedTokenAccount(
+        connection,
+        wallet.payer,
+        feeMint,
+        userB
+      );
+      await mintTo(
+        connection,
+        wallet.payer,
+        feeMint,
+        userBFeeTokenAccount.address,
+        wallet.publicKey,
+        10_000_000
+      );
+
+      const startTime = new anchor.BN(Math.floor(Date.now() / 1000));
+      await setSolPrice(startTime.toNumber());
+      await program.methods
+        .initializePresale({
+          name: "Native Token",
+          symbol: "NATIVE",
+          uri: "",
+          decimals: 9,
+          startTimestamp: startTime,
+          duration: new anchor.BN(100),
+          totalSupply: new anchor.BN(1_000_000),
+          roundAmounts: [new anchor.BN(800_000)],
+          roundPrices: [new anchor.BN(0.03 * PRECISION.toNumber())], // $0.03
+          roundEndTimes: [startTime.addn(100)],
+          roundPricingModes: [],
+          purchaseLimits: {
+            minAmount: new anchor.BN(0),
+            maxAmountPerWallet: new anchor.BN(0),
+            maxAmountPerTx: new anchor.BN(0),
+          },
+          roundPurchaseLimits: [],
+          roundMerkleRoots: [],
+          vesting: configData.vesting,
+          roundVestings: [],
+          referral: { rewardPercent: 0, rewardKind: { baseToken: {} } },
+          oracle: {
+            priceFeed,
+            maxPriceAge: new anchor.BN(60),
+            maxConfidencePercent: 100, // 1%
+          },
+          isProRata: false,
+          softCap: new anchor.BN(100_000),
+          hardCap: new anchor.BN(800_000),
+          unsoldAction: { burn: {} },
+          selloutCooldown: new anchor.BN(0),
+          liquidityAmount: new anchor.BN(200_000),
+          feePercent: configData.feePercent,
+          liquidityPercent: configData.liquidityPercent,
+        })
+        .accounts({
+          platformConfig,
+          platformWallet,
+          presaleConfig: nativePresaleConfig,
+          creator: userB,
+          baseMint: NATIVE_MINT,
+          newMint: nativeNewMint,
+          feeMint,
+          creatorFeeTokenAccount: userBFeeTokenAccount.address,
+          platformFeeTokenAccount: getPlatformFeeTokenAccount(),
+          vaultBaseTokenAccount: nativeVaultBaseTokenAccount,
+          vaultSol: nativeVaultSol,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          tokenProgram2022: TOKEN_2022_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([userBKeypair])
+        .rpc();
+
+      const presaleConfigAccount = await program.account.presaleConfig.fetch(
+        nativePresaleConfig
+      );
+      assert.equal(presaleConfigAccount.isNative, true, "isNative is wrong");
+    });
+
+    const buyWithSol = (amount: anchor.BN) =>
+      program.methods
+        .buyPretoken(0, amount, null)
+        .accounts({
+          platformConfig,
+          presaleConfig: nativePresaleConfig,
+          user: userC,
+          userAllocation: pda(
+            [
+              USER_ALLOCATION_TAG,
+              nativePresaleConfig.toBuffer(),
+              userC.toBuffer(),
+            ],
+            programId
+          ),
+          baseMint: NATIVE_MINT,
+          vaultBaseTokenAccount: nativeVaultBaseTokenAccount,
+          userBaseTokenAccount: null,
+          vaultSol: nativeVaultSol,
+          priceFeed,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([userCKeypair])
+        .rpc();
+
+    it("Buy Pretoken - feed not owned by the oracle - fail", async () => {
+      await setSolPrice(Math.floor(Date.now() / 1000));
+      const setOracleProgram = (oracle: PublicKey) =>
+        program.methods
+          .updateOracleProgram(oracle)
+          .accounts({ platformConfig, admin: platformWallet })
+          .signers([wallet.payer])
+          .rpc();
+      await setOracleProgram(ammProgram.programId);
+      try {
+        await buyWithSol(new anchor.BN(1_000).mul(PRECISION));
+      } catch (err) {
+        assert.equal(err.error.errorMessage, "Invalid price feed");
+      }
+      await setOracleProgram(oracleProgram.programId);
+    });
+
+    it("Buy Pretoken - userC buy with stale price - fail", async () => {
+      await setSolPrice(Math.floor(Date.now() / 1000) - 120);
+      try {
+        await buyWithSol(new anchor.BN(1_000).mul(PRECISION));
+      } catch (err) {
+        assert.equal(err.error.errorMessage, "Oracle price is stale");
+      }
+    });
+
+    it("Buy Pretoken - userC buy with SOL - success", async () => {
+      await setSolPrice(Math.floor(Date.now() / 1000));
+      const vaultBalanceBefore = await connection.getBalance(nativeVaultSol);
+
+      // 1000 pretokens at $0.03 is $30, or 0.2 SOL at $150
+      const txSig = await buyWithSol(new anchor.BN(1_000).mul(PRECISION));
+      console.log(`Transaction Signature: ${txSig}`);
+
+      const events = await getCpiEvents(program, txSig);
+      const bought = events.find((event) => event.name === "pretokenBought");
+      const vaultBalanceAfter = await connection.getBalance(nativeVaultSol);
+      assert.equal(
+        (vaultBalanceAfter - vaultBalanceBefore).toString(),
+        bought.data.baseAmount.toString(),
+        "vault SOL balance is wrong"
+      );
+      assert.equal(
+        bought.data.baseAmount.toString(),
+        new anchor.BN(0.2 * PRECISION.toNumber()).toString(),
+        "base amount is wrong"
+      );
+    });
+
+    const cancelPresale = (authority: Keypair) =>
+      program.methods
+        .cancelPresale()
+        .accounts({
+          platformConfig,
+          presaleConfig: nativePresaleConfig,
+          authority: authority.publicKey,
+        })
+        .signers([authority])
+        .rpc();
+
+    it("Cancel Presale - creator cancel after start - fail", async () => {
+      try {
+        await cancelPresale(userBKeypair);
+      } catch (err) {
+        assert.equal(err.error.errorMessage, "Presale already started");
+      }
+    });
+
+    it("Cancel Presale - admin cancel - success", async () => {
+      const txSig = await cancelPresale(wallet.payer);
+      console.log(`Transaction Signature: ${txSig}`);
+
+      const presaleConfigAccount = await program.account.presaleConfig.fetch(
+        nativePresaleConfig
+      );
+      assert.equal(
+        presaleConfigAccount.isCancelled,
+        true,
+        "isCancelled is wrong"
+      );
+      assert.equal(
+        presaleConfigAccount.isFinalized,
+        true,
+        "isFinalized is wrong"
+      );
+    });
+
+    it("Buy Pretoken - userC buy after cancel - fail", async () => {
+      try {
+        await buyWithSol(new anchor.BN(1_000).mul(PRECISION));
+      } catch (err) {
+        assert.equal(err.error.errorMessage, "Presale cancelled");
+      }
+    });
+
+    it("Refund - userC refund after cancel - success", async () => {
+      const txSig = await program.methods
+        .claimOrRefund()
+        .accounts({
+          presaleConfig: nativePresaleConfig,
+          user: userC,
+          userAllocation: pda(
+            [
+              USER_ALLOCATION_TAG,
+              nativePresaleConfig.toBuffer(),
+              userC.toBuffer(),
+            ],
+            programId
+          ),
+          newMint: nativeNewMint,
+          userTokenAccount: pda(
+            [
+              USER_TOKEN_ACCOUNT_TAG,
+              nativePresaleConfig.toBuffer(),
+              userC.toBuffer(),
+            ],
+            programId
+          ),
+          baseMint: NATIVE_MINT,
+          vaultBaseTokenAccount: nativeVaultBaseTokenAccount,
+          userBaseTokenAccount: null,
+          vaultSol: nativeVaultSol,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          tokenProgram2022: TOKEN_2022_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([userCKeypair])
+        .rpc();
+      console.log(`Transaction Signature: ${txSig}`);
+
+      // cancelled presales refund without the fee
+      const events = await getCpiEvents(program, txSig);
+      const refunded = events.find((event) => event.name === "refunded");
+      assert.equal(refunded.data.feeAmount.toString(), "0", "fee is wrong");
+      assert.equal(
+        refunded.data.baseAmount.toString(),
+        new anchor.BN(0.2 * PRECISION.toNumber()).toString(),
+        "refund amount is wrong"
+      );
+    });
+
+    it("Close - userC close allocation - success", async () => {
+      const userCAllocation = pda(
+        [
+          USER_ALLOCATION_TAG,
+          nativePresaleConfig.toBuffer(),
+          userC.toBuffer(),
+        ],
+        programId
+      );
+      const txSig = await program.methods
+        .closeUserAllocation()
+        .accounts({
+          presaleConfig: nativePresaleConfig,
+          user: userC,
+          userAllocation: userCAllocation,
+          userTokenAccount: pda(
+            [
+              USER_TOKEN_ACCOUNT_TAG,
+              nativePresaleConfig.toBuffer(),
+              userC.toBuffer(),
+            ],
+            programId
+          ),
+          tokenProgram2022: TOKEN_2022_PROGRAM_ID,
+        })
+        .signers([userCKeypair])
+        .rpc();
+      console.log(`Transaction Signature: ${txSig}`);
+
+      assert.isNull(
+        await connection.getAccountInfo(userCAllocation),
+        "allocation is not closed"
+      );
+      const presaleConfigAccount = await program.account.presaleConfig.fetch(
+        nativePresaleConfig
+      );
+      assert.equal(
+        presaleConfigAccount.participantCount.toString(),
+        "0",
+        "participantCount is wrong"
+      );
+    });
+
+    it("Close - userB close presale - success", async () => {
+      const txSig = await program.methods
+        .closePresale()
+        .accounts({
+          presaleConfig: nativePresaleConfig,
+          creator: userB,
+          baseMint: NATIVE_MINT,
+          vaultBaseTokenAccount: nativeVaultBaseTokenAccount,
+          creatorBaseTokenAccount: null,
+          vaultSol: nativeVaultSol,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([userBKeypair])
+        .rpc();
+      console.log(`Transaction Signature: ${txSig}`);
+
+      assert.isNull(
+        await connection.getAccountInfo(nativePresaleConfig),
+        "presale config is not closed"
+      );
+      assert.isNull(
+        await connection.getAccountInfo(nativeVaultBaseTokenAccount),
+        "vault is not closed"
+      );
+    });
+  });
+
+  // The scenario suites below each run their own single-round presale
+  // paid in baseMint, without liquidity unless a test overrides it
+  const getPresaleAccounts = (creator: PublicKey, presaleId = 0) => {
+    const presale = pda(
+      [PRESALE_CONFIG_TAG, creator.toBuffer(), presaleIdSeed(presaleId)],
+      programId
+    );
+    const mint = pda([NEW_MINT_TAG, presale.toBuffer()], programId);
+    const pool = pda(
+      [POOL_TAG, mint.toBuffer(), baseMint.toBuffer()],
+      ammProgram.programId
+    );
+    const lpMint = pda([LP_MINT_TAG, pool.toBuffer()], ammProgram.programId);
+    const liquidityAuthority = pda(
+      [LIQUIDITY_AUTHORITY_TAG, presale.toBuffer()],
+      programId
+    );
+    return {
+      presaleConfig: presale,
+      newMint: mint,
+      vaultBaseTokenAccount: pda(
+        [VAULT_BASE_TOKEN_ACCOUNT_TAG, presale.toBuffer()],
+        programId
+      ),
+      liquidityAuthority,
+      liquidityTokenAccount: pda(
+        [LIQUIDITY_TOKEN_ACCOUNT_TAG, presale.toBuffer()],
+        programId
+      ),
+      liquidityBaseTokenAccount: pda(
+        [LIQUIDITY_BASE_TOKEN_ACCOUNT_TAG, presale.toBuffer()],
+        programId
+      ),
+      ammPool: pool,
+      ammVaultNewTokenAccount: pda(
+        [POOL_VAULT_TAG, pool.toBuffer(), mint.toBuffer()],
+        ammProgram.programId
+      ),
+      ammVaultBaseTokenAccount: pda(
+        [POOL_VAULT_TAG, pool.toBuffer(), baseMint.toBuffer()],
+        ammProgram.programId
+      ),
+      ammLpMint: lpMint,
+      lpTokenAccount: getAssociatedTokenAddressSync(
+        lpMint,
+        liquidityAuthority,
+        true,
+        TOKEN_PROGRAM_ID,
+        ASSOCIATED_TOKEN_PROGRAM_ID
+      ),
+    };
+  };
+  type PresaleAccounts = ReturnType<typeof getPresaleAccounts>;
+
+  const getScenarioAllocation = (presale: PresaleAccounts, user: PublicKey) =>
+    pda(
+      [
+        USER_ALLOCATION_TAG,
+        presale.presaleConfig.toBuffer(),
+        user.toBuffer(),
+      ],
+      programId
+    );
+  const getScenarioTokenAccount = (presale: PresaleAccounts, user: PublicKey) =>
+    pda(
+      [
+        USER_TOKEN_ACCOUNT_TAG,
+        presale.presaleConfig.toBuffer(),
+        user.toBuffer(),
+      ],
+      programId
+    );
+
+  // funds a fresh creator with SOL and the platform fee
+  const prepareCreator = async (creatorKeypair: Keypair) => {
+    await safeAirdrop(connection, creatorKeypair.publicKey, 1_000_000_000);
+    const creatorFeeTokenAccount = await getOrCreateAssociatedTokenAccount(
+      connection,
+      wallet.payer,
+      feeMint,
+      creatorKeypair.publicKey
+    );
+    await mintTo(
+      connection,
+      wallet.payer,
+      feeMint,
+      creatorFeeTokenAccount.address,
+      wallet.publicKey,
+      100_000_000
+    );
+  };
+
+  // 1M pretokens in one round at 0.001 base token, starting now
+  const createPresale = async (
+    creatorKeypair: Keypair,
+    presaleId: number,
+    overrides: Record<string, any> = {}
+  ) => {
+    const creator = creatorKeypair.publicKey;
+    const presale = getPresaleAccounts(creator, presaleId);
+    const startTime = new anchor.BN(Math.floor(Date.now() / 1000));
+    const txSig = await program.methods
+      .initializePresale({
+        name: "Scenario Token",
+        symbol: "SCENE",
+        uri: "",
+        decimals: 9,
+        startTimestamp: startTime,
+        duration: new anchor.BN(100),
+        totalSupply: new anchor.BN(1_000_000),
+        roundAmounts: [new anchor.BN(1_000_000)],
+        roundPrices: [new anchor.BN(0.001 * PRECISION.toNumber())],
+        roundEndTimes: [startTime.addn(100)],
+        roundPricingModes: [],
+        purchaseLimits: {
+          minAmount: new anchor.BN(0),
+          maxAmountPerWallet: new anchor.BN(0),
+          maxAmountPerTx: new anchor.BN(0),
+        },
+        roundPurchaseLimits: [],
+        roundMerkleRoots: [],
+        vesting: configData.vesting,
+        roundVestings: [],
+        referral: { rewardPercent: 0, rewardKind: { baseToken: {} } },
+        oracle: null,
+        isProRata: false,
+        softCap: new anchor.BN(100_000),
+        hardCap: new anchor.BN(1_000_000),
+        unsoldAction: { burn: {} },
+        selloutCooldown: new anchor.BN(0),
+        liquidityAmount: new anchor.BN(0),
+        feePercent: configData.feePercent,
+        liquidityPercent: 0,
+        ...overrides,
+      })
+      .accounts({
+        platformConfig,
+        platformWallet,
+        presaleConfig: presale.presaleConfig,
+        creator,
+        baseMint,
+        newMint: presale.newMint,
+        feeMint,
+        creatorFeeTokenAccount: getAssociatedTokenAddressSync(feeMint, creator),
+        platformFeeTokenAccount: getPlatformFeeTokenAccount(),
+        vaultBaseTokenAccount: presale.vaultBaseTokenAccount,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        tokenProgram2022: TOKEN_2022_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([creatorKeypair])
+      .rpc();
+    console.log(`Transaction Signature: ${txSig}`);
+    return { ...presale, txSig };
+  };
+
+  const buyScenario = (
+    presale: PresaleAccounts,
+    userKeypair: Keypair,
+    userBaseTokenAccount: PublicKey,
+    amount: anchor.BN,
+    referrerStats: PublicKey = null
+  ) =>
+    program.methods
+      .buyPretoken(0, amount, null)
+      .accounts({
+        platformConfig,
+        presaleConfig: presale.presaleConfig,
+        user: userKeypair.publicKey,
+        userAllocation: getScenarioAllocation(presale, userKeypair.publicKey),
+        baseMint,
+        vaultBaseTokenAccount: presale.vaultBaseTokenAccount,
+        userBaseTokenAccount,
+        referrerStats,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([userKeypair])
+      .rpc();
+
+  const sellScenario = (
+    presale: PresaleAccounts,
+    userKeypair: Keypair,
+    userBaseTokenAccount: PublicKey,
+    amount: anchor.BN,
+    referrerStats: PublicKey = null
+  ) =>
+    program.methods
+      .sellPretoken(0, amount)
+      .accounts({
+        platformConfig,
+        presaleConfig: presale.presaleConfig,
+        user: userKeypair.publicKey,
+        userAllocation: getScenarioAllocation(presale, userKeypair.publicKey),
+        baseMint,
+        vaultBaseTokenAccount: presale.vaultBaseTokenAccount,
+        userBaseTokenAccount,
+        referrerStats,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([userKeypair])
+      .rpc();
+
+  const finalizeScenario = (
+    presale: PresaleAccounts,
+    creatorKeypair: Keypair,
+    creatorTokenAccount: PublicKey = null
+  ) =>
+    program.methods
+      .finalizePresale()
+      .accounts({
+        platformConfig,
+        presaleConfig: presale.presaleConfig,
+        creator: creatorKeypair.publicKey,
+        newMint: presale.newMint,
+        baseMint,
+        vaultBaseTokenAccount: presale.vaultBaseTokenAccount,
+        liquidityAuthority: presale.liquidityAuthority,
+        liquidityTokenAccount: presale.liquidityTokenAccount,
+        liquidityBaseTokenAccount: presale.liquidityBaseTokenAccount,
+        creatorTokenAccount,
+        ammPool: presale.ammPool,
+        ammVaultNewTokenAccount: presale.ammVaultNewTokenAccount,
+        ammVaultBaseTokenAccount: presale.ammVaultBaseTokenAccount,
+        ammLpMint: presale.ammLpMint,
+        lpTokenAccount: presale.lpTokenAccount,
+        ammProgram: ammProgram.programId,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        tokenProgram2022: TOKEN_2022_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([creatorKeypair])
+      .rpc();
+
+  const claimScenario = (
+    presale: PresaleAccounts,
+    userKeypair: Keypair,
+    userBaseTokenAccount: PublicKey
+  ) =>
+    program.methods
+      .claimOrRefund()
+      .accounts({
+        presaleConfig: presale.presaleConfig,
+        user: userKeypair.publicKey,
+        userAllocation: getScenarioAllocation(presale, userKeypair.publicKey),
+        newMint: presale.newMint,
+        userTokenAccount: getScenarioTokenAccount(
+          presale,
+          userKeypair.publicKey
+        ),
+        baseMint,
+        vaultBaseTokenAccount: presale.vaultBaseTokenAccount,
+        userBaseTokenAccount,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        tokenProgram2022: TOKEN_2022_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([userKeypair])
+      .rpc();
+
+  const sleep = (seconds: number) =>
+    new Promise((resolve) => setTimeout(resolve, seconds * 1000));
+
+  describe("Vesting", () => {
+    // 20% at TGE, then linear over 20 seconds after a 10 second cliff; the
+    // windows are wide enough for a few RPC round trips each
+    const vestingCreatorKeypair = new Keypair();
+    const cliffDuration = 10;
+    const vestingDuration = 20;
+    const vesting = {
+      tgePercent: 2000,
+      cliffDuration: new anchor.BN(cliffDuration),
+      vestingDuration: new anchor.BN(vestingDuration),
+    };
+    const totalAmount = new anchor.BN(1_000_000).mul(PRECISION);
+    const tgeAmount = totalAmount.muln(2000).divn(10000);
+    let presale: PresaleAccounts;
+    let finalizedTimestamp: number;
+
+    const getClaimedAmount = async () =>
+      (
+        await program.account.userAllocation.fetch(
+          getScenarioAllocation(presale, userA)
+        )
+      ).claimedAmount;
+    // vesting runs on the validator's clock, not the test runner's
+    const getClockTimestamp = async () => {
+      const clock = await connection.getAccountInfo(
+        SYSVAR_CLOCK_PUBKEY,
+        "processed"
+      );
+      return Number(clock.data.readBigInt64LE(32));
+    };
+    const waitForClock = async (timestamp: number) => {
+      while ((await getClockTimestamp()) < timestamp) {
+        await sleep(1);
+      }
+    };
+
+    it("Vesting - userA buy out and finalize - success", async () => {
+      await prepareCreator(vestingCreatorKeypair);
+      presale = await createPresale(vestingCreatorKeypair, 0, { vesting });
+      await buyScenario(
+        presale,
+        userAKeypair,
+        userABaseTokenAccount,
+        totalAmount
+      );
+
+      // selling out with no cooldown ends the presale right away
+      const txSig = await finalizeScenario(presale, vestingCreatorKeypair);
+      console.log(`Transaction Signature: ${txSig}`);
+      const presaleConfigAccount = await program.account.presaleConfig.fetch(
+        presale.presaleConfig
+      );
+      assert.equal(presaleConfigAccount.isSuccess, true, "isSuccess is wrong");
+      finalizedTimestamp = presaleConfigAccount.finalizedTimestamp.toNumber();
+    });
+
+    it("Vesting - userA claim at TGE - success", async () => {
+      await claimScenario(presale, userAKeypair, userABaseTokenAccount);
+      assert.isBelow(
+        await getClockTimestamp(),
+        finalizedTimestamp + cliffDuration,
+        "claimed after the cliff"
+      );
+      assert.equal(
+        (await getClaimedAmount()).toString(),
+        tgeAmount.toString(),
+        "TGE amount is wrong"
+      );
+    });
+
+    it("Vesting - userA claim during cliff - fail", async () => {
+      try {
+        await claimScenario(presale, userAKeypair, userABaseTokenAccount);
+        assert.fail("claim during cliff should fail");
+      } catch (err) {
+        assert.equal(err.error.errorMessage, "Nothing to claim");
+      }
+      assert.isBelow(
+        await getClockTimestamp(),
+        finalizedTimestamp + cliffDuration,
+        "claimed after the cliff"
+      );
+      assert.equal(
+        (await getClaimedAmount()).toString(),
+        tgeAmount.toString(),
+        "claimedAmount is wrong"
+      );
+    });
+
+    it("Vesting - userA claim mid vesting - success", async () => {
+      // halfway through the linear part
+      await waitForClock(
+        finalizedTimestamp + cliffDuration + vestingDuration / 2
+      );
+
+      const txSig = await claimScenario(
+        presale,
+        userAKeypair,
+        userABaseTokenAccount
+      );
+      const tx = await connection.getTransaction(txSig, {
+        commitment: "confirmed",
+        maxSupportedTransactionVersion: 0,
+      });
+      const vestingElapsed = tx.blockTime - finalizedTimestamp - cliffDuration;
+      assert.isBelow(vestingElapsed, vestingDuration, "vesting ended");
+      const expectedAmount = tgeAmount.add(
+        totalAmount.sub(tgeAmount).muln(vestingElapsed).divn(vestingDuration)
+      );
+      assert.equal(
+        (await getClaimedAmount()).toString(),
+        expectedAmount.toString(),
+        "vested amount is wrong"
+      );
+    });
+
+    it("Vesting - userA claim after vesting ends - success", async () => {
+      await waitForClock(finalizedTimestamp + cliffDuration + vestingDuration);
+
+      await claimScenario(presale, userAKeypair, userABaseTokenAccount);
+      assert.equal(
+        (await getClaimedAmount()).toString(),
+        totalAmount.toString(),
+        "claimedAmount is wrong"
+      );
+      const userTokenAccount = await getAccount(
+        connection,
+        getScenarioTokenAccount(presale, userA),
+        "processed",
+        TOKEN_2022_PROGRAM_ID
+      );
+      assert.equal(
+        userTokenAccount.amount.toString(),
+        totalAmount.toString(),
+        "userA balance is wrong"
+      );
+    });
+  });
+
+  describe("Referral", () => {
+    // userA refers userB and userC into a 5% base token reward presale
+    const referralCreatorKeypair = new Keypair();
+    let presale: PresaleAccounts;
+    let referrerStats: PublicKey;
+    const amount = (tokens: number) => new anchor.BN(tokens).mul(PRECISION);
+
+    const fetchReferrerStats = () =>
+      program.account.referrerStats.fetch(referrerStats);
+    const fetchAllocation = (user: PublicKey) =>
+      program.account.userAllocation.fetch(
+        getScenarioAllocation(presale, user)
+      );
+
+    it("Referral - userB and userC buy through userA - success", async () => {
+      await prepareCreator(referralCreatorKeypair);
+      presale = await createPresale(referralCreatorKeypair, 0, {
+        referral: configData.referral,
+      });
+      referrerStats = pda(
+        [
+          REFERRER_STATS_TAG,
+          presale.presaleConfig.toBuffer(),
+          userA.toBuffer(),
+        ],
+        programId
+      );
+      await program.methods
+        .registerReferrer()
+        .accounts({
+          presaleConfig: presale.presaleConfig,
+          referrer: userA,
+          referrerStats,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([userAKeypair])
+        .rpc();
+
+      // userB's first 10k are bought before it has a referrer
+      await buyScenario(
+        presale,
+        userBKeypair,
+        userBBaseTokenAccount,
+        amount(10_000)
+      );
+      await buyScenario(
+        presale,
+        userBKeypair,
+        userBBaseTokenAccount,
+        amount(10_000),
+        referrerStats
+      );
+      await buyScenario(
+        presale,
+        userCKeypair,
+        userCBaseTokenAccount,
+        amount(10_000),
+        referrerStats
+      );
+
+      const stats = await fetchReferrerStats();
+      assert.equal(
+        stats.referralCount.toString(),
+        "2",
+        "referralCount is wrong"
+      );
+      assert.equal(
+        stats.referredAmount.toString(),
+        amount(20_000).toString(),
+        "referredAmount is wrong"
+      );
+      const userBAllocation = await fetchAllocation(userB);
+      assert.equal(
+        userBAllocation.referredAmount.toString(),
+        amount(10_000).toString(),
+        "userB referredAmount is wrong"
+      );
+    });
+
+    it("Referral - userC sell after referral - success", async () => {
+      const userCBefore = await fetchAllocation(userC);
+      const statsBefore = await fetchReferrerStats();
+
+      await sellScenario(
+        presale,
+        userCKeypair,
+        userCBaseTokenAccount,
+        amount(4_000),
+        referrerStats
+      );
+
+      // 4k of userC's 10k referred pretokens give back 40% of its reward
+      const revokedReward = userCBefore.referralBaseRewardAmount
+        .muln(4)
+        .divn(10);
+      const stats = await fetchReferrerStats();
+      assert.equal(
+        stats.referredAmount.toString(),
+        amount(16_000).toString(),
+        "referredAmount is wrong"
+      );
+      assert.equal(
+        stats.baseRewardAmount.toString(),
+        statsBefore.baseRewardAmount.sub(revokedReward).toString(),
+        "baseRewardAmount is wrong"
+      );
+      const userCAfter = await fetchAllocation(userC);
+      assert.equal(
+        userCAfter.referredAmount.toString(),
+        amount(6_000).toString(),
+        "userC referredAmount is wrong"
+      );
+    });
+
+    it("Referral - userB sell more than referred - success", async () => {
+      const userCAllocation = await fetchAllocation(userC);
+
+      // 15k spans userB's 10k referred pretokens and 5k bought before
+      await sellScenario(
+        presale,
+        userBKeypair,
+        userBBaseTokenAccount,
+        amount(15_000),
+        referrerStats
+      );
+
+      // only userB's own reward is revoked, userC's is kept
+      const stats = await fetchReferrerStats();
+      assert.equal(
+        stats.referredAmount.toString(),
+        userCAllocation.referredAmount.toString(),
+        "referredAmount is wrong"
+      );
+      assert.equal(
+        stats.baseRewardAmount.toString(),
+        userCAllocation.referralBaseRewardAmount.toString(),
+        "baseRewardAmount is wrong"
+      );
+      const userBAllocation = await fetchAllocation(userB);
+      assert.equal(
+        userBAllocation.referredAmount.toString(),
+        "0",
+        "userB referredAmount is wrong"
+      );
+      assert.equal(
+        userBAllocation.referralBaseRewardAmount.toString(),
+        "0",
+        "userB referralBaseRewardAmount is wrong"
+      );
+      const presaleConfigAccount = await program.account.presaleConfig.fetch(
+        presale.presaleConfig
+      );
+      assert.equal(
+        presaleConfigAccount.referralBaseAmount.toString(),
+        stats.baseRewardAmount.toString(),
+        "referralBaseAmount is wrong"
+      );
+    });
+  });
+
+  describe("Pretoken Referral", () => {
+    // 10% pretoken rewards are reserved out of the 1M round supply
+    const pretokenCreatorKeypair = new Keypair();
+    const tokens = (amount: number) => new anchor.BN(amount).mul(PRECISION);
+    let presale: PresaleAccounts;
+    let endTime: number;
+    let referrerStats: PublicKey;
+
+    it("Pretoken Referral - userB buy past the reserve - fail", async () => {
+      await prepareCreator(pretokenCreatorKeypair);
+      const startTime = Math.floor(Date.now() / 1000);
+      endTime = startTime + 10;
+      presale = await createPresale(pretokenCreatorKeypair, 0, {
+        startTimestamp: new anchor.BN(startTime),
+        duration: new anchor.BN(10),
+        roundEndTimes: [new anchor.BN(endTime)],
+        referral: { rewardPercent: 1000, rewardKind: { pretoken: {} } },
+      });
+      referrerStats = pda(
+        [
+          REFERRER_STATS_TAG,
+          presale.presaleConfig.toBuffer(),
+          userA.toBuffer(),
+        ],
+        programId
+      );
+      await program.methods
+        .registerReferrer()
+        .accounts({
+          presaleConfig: presale.presaleConfig,
+          referrer: userA,
+          referrerStats,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([userAKeypair])
+        .rpc();
+
+      // 950k and a 95k reward do not fit in 1M
+      try {
+        await buyScenario(
+          presale,
+          userBKeypair,
+          userBBaseTokenAccount,
+          tokens(950_000),
+          referrerStats
+        );
+        assert.fail("buy past the referral reserve should fail");
+      } catch (err) {
+        assert.equal(
+          err.error.errorMessage,
+          "Not enough supply left for referral rewards"
+        );
+      }
+    });
+
+    it("Pretoken Referral - userB buy in the reserve - success", async () => {
+      await buyScenario(
+        presale,
+        userBKeypair,
+        userBBaseTokenAccount,
+        tokens(900_000),
+        referrerStats
+      );
+      const presaleConfigAccount = await program.account.presaleConfig.fetch(
+        presale.presaleConfig
+      );
+      assert.equal(
+        presaleConfigAccount.referralPretokenAmount.toString(),
+        tokens(90_000).toString(),
+        "referralPretokenAmount is wrong"
+      );
+    });
+
+    it("Pretoken Referral - finalize and claim - success", async () => {
+      while (Date.now() / 1000 < endTime + 1) {
+        await sleep(1);
+      }
+      await finalizeScenario(presale, pretokenCreatorKeypair);
+      const presaleConfigAccount = await program.account.presaleConfig.fetch(
+        presale.presaleConfig
+      );
+      // the reserved rewards are not part of the burned unsold supply
+      assert.equal(
+        presaleConfigAccount.unsoldAmount.toString(),
+        tokens(10_000).toString(),
+        "unsoldAmount is wrong"
+      );
+
+      await claimScenario(presale, userBKeypair, userBBaseTokenAccount);
+      await program.methods
+        .claimReferralRewards()
+        .accounts({
+          presaleConfig: presale.presaleConfig,
+          referrer: userA,
+          referrerStats,
+          newMint: presale.newMint,
+          referrerTokenAccount: getScenarioTokenAccount(presale, userA),
+          baseMint,
+          vaultBaseTokenAccount: presale.vaultBaseTokenAccount,
+          referrerBaseTokenAccount: userABaseTokenAccount,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          tokenProgram2022: TOKEN_2022_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([userAKeypair])
+        .rpc();
+
+      // bought, rewarded and burned pretokens add up to the total supply
+      const mint = await getMint(
+        connection,
+        presale.newMint,
+        "processed",
+        TOKEN_2022_PROGRAM_ID
+      );
+      assert.equal(
+        new anchor.BN(mint.supply.toString())
+          .add(presaleConfigAccount.unsoldAmount)
+          .toString(),
+        tokens(1_000_000).toString(),
+        "minted supply is wrong"
+      );
+    });
+  });
+
+  describe("Linear Pricing", () => {
+    // the price rises linearly from 0.001 to 0.003 base token across the
+    // round's 1M pretokens
+    const curveCreatorKeypair = new Keypair();
+    const totalAmount = new anchor.BN(1_000_000).mul(PRECISION);
+    const startPrice = new anchor.BN(0.001 * PRECISION.toNumber());
+    const endPrice = new anchor.BN(0.003 * PRECISION.toNumber());
+    let presale: PresaleAccounts;
+
+    // the integral of the price from `from` to `to`, rounded up for buys
+    // and down for sells
+    const curveIntegral = (from: anchor.BN, to: anchor.BN) => ({
+      numerator: to
+        .sub(from)
+        .mul(
+          totalAmount
+            .muln(2)
+            .mul(startPrice)
+            .add(endPrice.sub(startPrice).mul(from.add(to)))
+        ),
+      denominator: totalAmount.muln(2).mul(PRECISION),
+    });
+    const curveCost = (from: anchor.BN, to: anchor.BN) => {
+      const { numerator, denominator } = curveIntegral(from, to);
+      return numerator.add(denominator).subn(1).div(denominator);
+    };
+    const curveValue = (from: anchor.BN, to: anchor.BN) => {
+      const { numerator, denominator } = curveIntegral(from, to);
+      return numerator.div(denominator);
+    };
+    const getEvent = async (txSig: string, name: string) =>
+      (await getCpiEvents(program, txSig)).find(
+        (event) => event.name === name
+      );
+
+    it("Linear Pricing - userA buy 100k - success", async () => {
+      await prepareCreator(curveCreatorKeypair);
+      presale = await createPresale(curveCreatorKeypair, 0, {
+        roundPrices: [startPrice],
+        roundPricingModes: [{ linear: { endPrice } }],
+      });
+
+      const amount = new anchor.BN(100_000).mul(PRECISION);
+      const txSig = await buyScenario(
+        presale,
+        userAKeypair,
+        userABaseTokenAccount,
+        amount
+      );
+      const bought = await getEvent(txSig, "pretokenBought");
+      // 100k at an average of 0.0011 base token
+      assert.equal(
+        bought.data.baseAmount.toString(),
+        new anchor.BN(110).mul(PRECISION).toString(),
+        "base amount is wrong"
+      );
+      assert.equal(
+        bought.data.baseAmount.toString(),
+        curveCost(new anchor.BN(0), amount).toString(),
+        "base amount is wrong"
+      );
+    });
+
+    it("Linear Pricing - userB buy one unit rounds up - success", async () => {
+      const from = new anchor.BN(100_000).mul(PRECISION);
+      const txSig = await buyScenario(
+        presale,
+        userBKeypair,
+        userBBaseTokenAccount,
+        new anchor.BN(1)
+      );
+      const bought = await getEvent(txSig, "pretokenBought");
+      // a fraction of a base unit is charged as a whole one
+      assert.equal(
+        bought.data.baseAmount.toString(),
+        curveCost(from, from.addn(1)).toString(),
+        "base amount is wrong"
+      );
+      assert.equal(bought.data.baseAmount.toString(), "1", "not rounded up");
+    });
+
+    it("Linear Pricing - userA sell down the curve - success", async () => {
+      const allocation = await program.account.userAllocation.fetch(
+        getScenarioAllocation(presale, userA)
+      );
+      const amount = new anchor.BN(40_000).mul(PRECISION);
+      // userA's 100k and userB's single unit are sold so far
+      const sold = new anchor.BN(100_000).mul(PRECISION).addn(1);
+
+      const txSig = await sellScenario(
+        presale,
+        userAKeypair,
+        userABaseTokenAccount,
+        amount
+      );
+      const soldEvent = await getEvent(txSig, "pretokenSold");
+      // the top 40k of the curve, rounded down
+      const value = curveValue(sold.sub(amount), sold);
+      const feeAmount = value.muln(configData.feePercent).divn(10_000);
+      assert.equal(
+        soldEvent.data.feeAmount.toString(),
+        feeAmount.toString(),
+        "fee amount is wrong"
+      );
+      assert.equal(
+        soldEvent.data.baseAmount.toString(),
+        value.sub(feeAmount).toString(),
+        "base amount is wrong"
+      );
+
+      const allocationAfter = await program.account.userAllocation.fetch(
+        getScenarioAllocation(presale, userA)
+      );
+      assert.equal(
+        allocationAfter.baseAmount.toString(),
+        allocation.baseAmount.sub(value).toString(),
+        "user base amount is wrong"
+      );
+    });
+  });
+
+  describe("Pro-rata", () => {
+    // userA commits 1.2M and userB 0.8M to a 1M round, so both get half
+    const proRataCreatorKeypair = new Keypair();
+    const commitments = [
+      { keypair: userAKeypair, amount: new anchor.BN(1_200_000) },
+      { keypair: userBKeypair, amount: new anchor.BN(800_000) },
+    ];
+    const getBaseTokenAccount = (user: PublicKey) =>
+      user.equals(userA) ? userABaseTokenAccount : userBBaseTokenAccount;
+    let presale: PresaleAccounts;
+
+    it("Pro-rata - userA and userB oversubscribe - success", async () => {
+      await prepareCreator(proRataCreatorKeypair);
+      const endTime = Math.floor(Date.now() / 1000) + 5;
+      presale = await createPresale(proRataCreatorKeypair, 0, {
+        isProRata: true,
+        duration: new anchor.BN(5),
+        roundEndTimes: [new anchor.BN(endTime)],
+      });
+
+      for (const { keypair, amount } of commitments) {
+        await buyScenario(
+          presale,
+          keypair,
+          getBaseTokenAccount(keypair.publicKey),
+          amount.mul(PRECISION)
+        );
+      }
+      const presaleConfigAccount = await program.account.presaleConfig.fetch(
+        presale.presaleConfig
+      );
+      assert.equal(
+        presaleConfigAccount.roundConfigs[0].committedAmount.toString(),
+        new anchor.BN(2_000_000).mul(PRECISION).toString(),
+        "committedAmount is wrong"
+      );
+    });
+
+    it("Pro-rata - finalize after the end - success", async () => {
+      const { startTimestamp, duration } =
+        await program.account.presaleConfig.fetch(presale.presaleConfig);
+      while (Date.now() / 1000 < startTimestamp.add(duration).toNumber() + 1) {
+        await sleep(1);
+      }
+
+      await finalizeScenario(presale, proRataCreatorKeypair);
+      const presaleConfigAccount = await program.account.presaleConfig.fetch(
+        presale.presaleConfig
+      );
+      assert.equal(
+        presaleConfigAccount.totalBuyAmount.toString(),
+        new anchor.BN(1_000_000).mul(PRECISION).toString(),
+        "totalBuyAmount is wrong"
+      );
+    });
+
+    it("Pro-rata - userA and userB claim with refunds - success", async () => {
+      for (const { keypair, amount } of commitments) {
+        const user = keypair.publicKey;
+        const txSig = await claimScenario(
+          presale,
+          keypair,
+          getBaseTokenAccount(user)
+        );
+        const claimed = (await getCpiEvents(program, txSig)).find(
+          (event) => event.name === "claimed"
+        );
+
+        // half the commitment is allocated and half its cost refunded
+        const allocation = await program.account.userAllocation.fetch(
+          getScenarioAllocation(presale, user)
+        );
+        assert.equal(
+          allocation.amounts[0].toString(),
+          amount.divn(2).mul(PRECISION).toString(),
+          "allocation is wrong"
+        );
+        const excessBaseAmount = amount.divn(2).mul(PRECISION).divn(1000);
+        assert.equal(
+          claimed.data.excessBaseAmount.toString(),
+          excessBaseAmount.toString(),
+          "excessBaseAmount is wrong"
+        );
+        assert.equal(
+          allocation.baseAmount.toString(),
+          excessBaseAmount.toString(),
+          "baseAmount is wrong"
+        );
+      }
+    });
+  });
+
+  describe("Caps and Unsold Supply", () => {
+    // three 1M pretoken presales capped at 100k soft and 500k hard, one per
+    // unsold action
+    const capsCreatorKeypair = new Keypair();
+    const capsCreator = capsCreatorKeypair.publicKey;
+    const tokens = (amount: number) => new anchor.BN(amount).mul(PRECISION);
+    let burnPresale: PresaleAccounts;
+    let returnPresale: PresaleAccounts;
+    let liquidityPresale: PresaleAccounts;
+    let creatorTokenAccount: PublicKey;
+
+    const expectInvalidCap = async (overrides: Record<string, any>) => {
+      try {
+        await createPresale(capsCreatorKeypair, 0, overrides);
+      } catch (err) {
+        assert.equal(err.error.errorMessage, "Invalid soft or hard cap");
+      }
+    };
+
+    it("Initialize Presale - zero soft cap - fail", async () => {
+      await prepareCreator(capsCreatorKeypair);
+      await expectInvalidCap({ softCap: new anchor.BN(0) });
+    });
+
+    it("Initialize Presale - soft cap above hard cap - fail", async () => {
+      await expectInvalidCap({
+        softCap: new anchor.BN(600_000),
+        hardCap: new anchor.BN(500_000),
+      });
+    });
+
+    it("Initialize Presale - hard cap above round supply - fail", async () => {
+      await expectInvalidCap({ hardCap: new anchor.BN(1_000_001) });
+    });
+
+    it("Caps - one presale per unsold action - success", async () => {
+      const endTime = Math.floor(Date.now() / 1000) + 10;
+      const capped = {
+        hardCap: new anchor.BN(500_000),
+        duration: new anchor.BN(10),
+        roundEndTimes: [new anchor.BN(endTime)],
+      };
+      burnPresale = await createPresale(capsCreatorKeypair, 0, {
+        ...capped,
+        unsoldAction: { burn: {} },
+      });
+      returnPresale = await createPresale(capsCreatorKeypair, 1, {
+        ...capped,
+        unsoldAction: { returnToCreator: {} },
+      });
+      liquidityPresale = await createPresale(capsCreatorKeypair, 2, {
+        ...capped,
+        unsoldAction: { addToLiquidity: {} },
+        liquidityPercent: configData.liquidityPercent,
+      });
+      creatorTokenAccount = (
+        await getOrCreateAssociatedTokenAccount(
+          connection,
+          wallet.payer,
+          returnPresale.newMint,
+          capsCreator,
+          false,
+          "processed",
+          undefined,
+          TOKEN_2022_PROGRAM_ID
+        )
+      ).address;
+    });
+
+    it("Caps - userC buy between soft and hard cap - success", async () => {
+      for (const presale of [burnPresale, returnPresale]) {
+        await buyScenario(
+          presale,
+          userCKeypair,
+          userCBaseTokenAccount,
+          tokens(300_000)
+        );
+      }
+    });
+
+    it("Caps - userC buy up to the hard cap - success", async () => {
+      // asking for more than is left under the cap buys only the rest
+      await buyScenario(
+        liquidityPresale,
+        userCKeypair,
+        userCBaseTokenAccount,
+        tokens(600_000)
+      );
+      const presaleConfigAccount = await program.account.presaleConfig.fetch(
+        liquidityPresale.presaleConfig
+      );
+      assert.equal(
+        presaleConfigAccount.totalBuyAmount.toString(),
+        tokens(500_000).toString(),
+        "totalBuyAmount is wrong"
+      );
+    });
+
+    it("Caps - userB buy past the hard cap - fail", async () => {
+      try {
+        await buyScenario(
+          liquidityPresale,
+          userBKeypair,
+          userBBaseTokenAccount,
+          tokens(1)
+        );
+      } catch (err) {
+        assert.equal(err.error.errorMessage, "Hard cap reached");
+      }
+    });
+
+    it("Caps - finalize and burn the unsold supply - success", async () => {
+      const { startTimestamp, duration } =
+        await program.account.presaleConfig.fetch(burnPresale.presaleConfig);
+      while (Date.now() / 1000 < startTimestamp.add(duration).toNumber() + 1) {
+        await sleep(1);
+      }
+
+      await finalizeScenario(burnPresale, capsCreatorKeypair);
+      const presaleConfigAccount = await program.account.presaleConfig.fetch(
+        burnPresale.presaleConfig
+      );
+      assert.equal(presaleConfigAccount.isSuccess, true, "isSuccess is wrong");
+      assert.equal(
+        presaleConfigAccount.unsoldAmount.toString(),
+        tokens(700_000).toString(),
+        "unsoldAmount is wrong"
+      );
+      // nothing is minted for the burnt part
+      const newMintAccount = await getMint(
+        connection,
+        burnPresale.newMint,
+        "processed",
+        TOKEN_2022_PROGRAM_ID
+      );
+      assert.equal(newMintAccount.supply.toString(), "0", "supply is wrong");
+    });
+
+    it("Caps - finalize and return the unsold supply - success", async () => {
+      await finalizeScenario(
+        returnPresale,
+        capsCreatorKeypair,
+        creatorTokenAccount
+      );
+      const presaleConfigAccount = await program.account.presaleConfig.fetch(
+        returnPresale.presaleConfig
+      );
+      assert.equal(presaleConfigAccount.isSuccess, true, "isSuccess is wrong");
+      const creatorTokenAccountAfter = await getAccount(
+        connection,
+        creatorTokenAccount,
+        "processed",
+        TOKEN_2022_PROGRAM_ID
+      );
+      assert.equal(
+        creatorTokenAccountAfter.amount.toString(),
+        tokens(700_000).toString(),
+        "creator balance is wrong"
+      );
+    });
+
+    it("Caps - finalize and pool the unsold supply - success", async () => {
+      await finalizeScenario(liquidityPresale, capsCreatorKeypair);
+      const presaleConfigAccount = await program.account.presaleConfig.fetch(
+        liquidityPresale.presaleConfig
+      );
+      assert.equal(presaleConfigAccount.isSuccess, true, "isSuccess is wrong");
+      assert.equal(
+        presaleConfigAccount.liquidityAmount.toString(),
+        tokens(500_000).toString(),
+        "liquidityAmount is wrong"
+      );
+      const pool = await ammProgram.account.pool.fetch(
+        liquidityPresale.ammPool
+      );
+      assert.equal(
+        pool.reserveA.toString(),
+        tokens(500_000).toString(),
+        "pool new token reserve is wrong"
+      );
+    });
+  });
+
+  describe("Sellout Cooldown", () => {
+    // selling out opens finalization 4 seconds later, long before the end
+    const selloutCreatorKeypair = new Keypair();
+    const selloutCooldown = 4;
+    const tokens = (amount: number) => new anchor.BN(amount).mul(PRECISION);
+    let presale: PresaleAccounts;
+
+    const fetchPresale = () =>
+      program.account.presaleConfig.fetch(presale.presaleConfig);
+    const expectNotEnded = async () => {
+      try {
+        await finalizeScenario(presale, selloutCreatorKeypair);
+      } catch (err) {
+        assert.equal(err.error.errorMessage, "Presale not ended");
+      }
+      assert.equal((await fetchPresale()).isFinalized, false, "finalized");
+    };
+    const waitForCooldown = async () => {
+      const { selloutTimestamp } = await fetchPresale();
+      while (
+        Date.now() / 1000 <
+        selloutTimestamp.toNumber() + selloutCooldown + 1
+      ) {
+        await sleep(1);
+      }
+    };
+
+    it("Sellout - userA buy out the round - success", async () => {
+      await prepareCreator(selloutCreatorKeypair);
+      presale = await createPresale(selloutCreatorKeypair, 0, {
+        selloutCooldown: new anchor.BN(selloutCooldown),
+      });
+      await buyScenario(
+        presale,
+        userAKeypair,
+        userABaseTokenAccount,
+        tokens(1_000_000)
+      );
+      assert.isTrue(
+        (await fetchPresale()).selloutTimestamp.gtn(0),
+        "selloutTimestamp is wrong"
+      );
+    });
+
+    it("Sellout - finalize during the cooldown - fail", async () => {
+      await expectNotEnded();
+    });
+
+    it("Sellout - finalize after a sell clears it - fail", async () => {
+      const { selloutTimestamp } = await fetchPresale();
+      await sellScenario(
+        presale,
+        userAKeypair,
+        userABaseTokenAccount,
+        tokens(1_000)
+      );
+      assert.equal(
+        (await fetchPresale()).selloutTimestamp.toString(),
+        "0",
+        "selloutTimestamp is wrong"
+      );
+
+      // the old cooldown has passed, but the round is no longer sold out
+      while (
+        Date.now() / 1000 <
+        selloutTimestamp.toNumber() + selloutCooldown + 1
+      ) {
+        await sleep(1);
+      }
+      await expectNotEnded();
+    });
+
+    it("Sellout - finalize after the cooldown - success", async () => {
+      await buyScenario(
+        presale,
+        userAKeypair,
+        userABaseTokenAccount,
+        tokens(1_000)
+      );
+      await expectNotEnded();
+      await waitForCooldown();
+
+      await finalizeScenario(presale, selloutCreatorKeypair);
+      const presaleConfigAccount = await fetchPresale();
+      assert.equal(
+        presaleConfigAccount.isFinalized,
+        true,
+        "isFinalized is wrong"
+      );
+      assert.equal(presaleConfigAccount.isSuccess, true, "isSuccess is wrong");
+    });
+  });
+
+  describe("Second Presale", () => {
+    // the creator's second presale lives at presale id 1 next to the first
+    const secondCreatorKeypair = new Keypair();
+    const secondCreator = secondCreatorKeypair.publicKey;
+    let firstPresale: PresaleAccounts;
+    let secondPresale: PresaleAccounts;
+
+    it("Second Presale - creator initialize presale 1 - success", async () => {
+      await prepareCreator(secondCreatorKeypair);
+      firstPresale = await createPresale(secondCreatorKeypair, 0);
+      secondPresale = await createPresale(secondCreatorKeypair, 1);
+
+      assert.notEqual(
+        secondPresale.presaleConfig.toBase58(),
+        firstPresale.presaleConfig.toBase58(),
+        "presale config is reused"
+      );
+      const presaleConfigAccount = await program.account.presaleConfig.fetch(
+        secondPresale.presaleConfig
+      );
+      assert.equal(
+        presaleConfigAccount.presaleId.toString(),
+        "1",
+        "presaleId is wrong"
+      );
+      const creatorCounterAccount = await program.account.creatorCounter.fetch(
+        pda([CREATOR_COUNTER_TAG, secondCreator.toBuffer()], programId)
+      );
+      assert.equal(
+        creatorCounterAccount.presaleCount.toString(),
+        "2",
+        "presaleCount is wrong"
+      );
+    });
+
+    it("Second Presale - userA buy out presale 1 - success", async () => {
+      const amount = new anchor.BN(1_000_000).mul(PRECISION);
+      await buyScenario(
+        secondPresale,
+        userAKeypair,
+        userABaseTokenAccount,
+        amount
+      );
+
+      const userAAllocation = await program.account.userAllocation.fetch(
+        getScenarioAllocation(secondPresale, userA)
+      );
+      assert.equal(
+        userAAllocation.amounts[0].toString(),
+        amount.toString(),
+        "amount is wrong"
+      );
+      assert.isNull(
+        await connection.getAccountInfo(
+          getScenarioAllocation(firstPresale, userA)
+        ),
+        "first presale allocation is created"
+      );
+    });
+
+    it("Second Presale - finalize presale 1 - success", async () => {
+      await finalizeScenario(secondPresale, secondCreatorKeypair);
+
+      const secondPresaleAccount = await program.account.presaleConfig.fetch(
+        secondPresale.presaleConfig
+      );
+      assert.equal(
+        secondPresaleAccount.isFinalized,
+        true,
+        "isFinalized is wrong"
+      );
+      assert.equal(secondPresaleAccount.isSuccess, true, "isSuccess is wrong");
+      const firstPresaleAccount = await program.account.presaleConfig.fetch(
+        firstPresale.presaleConfig
+      );
+      assert.equal(
+        firstPresaleAccount.isFinalized,
+        false,
+        "first presale is finalized"
+      );
+    });
+  });
+
+  describe("Special Symbols", () => {
+    // "safe" suffix, "SAFU" exact and "moon" prefix symbols pay the special
+    // fee whatever their case
+    const symbolCreatorKeypair = new Keypair();
+    const rules = [
+      { kind: { suffix: {} }, pattern: "safe" },
+      { kind: { exact: {} }, pattern: "SAFU" },
+      { kind: { prefix: {} }, pattern: "moon" },
+    ];
+
+    const expectInvalidRules = async (invalidRules: typeof rules) => {
+      try {
+        await program.methods
+          .updateSpecialSymbolRules(invalidRules)
+          .accounts({ platformConfig, admin: platformWallet })
+          .signers([wallet.payer])
+          .rpc();
+      } catch (err) {
+        assert.equal(err.error.errorMessage, "Invalid special symbol rules");
+      }
+      const platformConfigAccount = await program.account.platformConfig.fetch(
+        platformConfig
+      );
+      assert.equal(
+        platformConfigAccount.specialSymbolRules.length,
+        2,
+        "specialSymbolRules is wrong"
+      );
+    };
+    const expectPlatformFee = async (
+      presaleId: number,
+      symbol: string,
+      isSpecial: boolean
+    ) => {
+      const { txSig } = await createPresale(symbolCreatorKeypair, presaleId, {
+        symbol,
+      });
+      const platformConfigAccount = await program.account.platformConfig.fetch(
+        platformConfig
+      );
+      const initialized = (await getCpiEvents(program, txSig)).find(
+        (event) => event.name === "presaleInitialized"
+      );
+      assert.equal(
+        initialized.data.platformFeeAmount.toString(),
+        (isSpecial
+          ? platformConfigAccount.feeAmountSpecial
+          : platformConfigAccount.feeAmountNormal
+        ).toString(),
+        `platformFeeAmount of ${symbol} is wrong`
+      );
+    };
+
+    it("Update special symbol rules - pattern too long - fail", async () => {
+      await expectInvalidRules([
+        { kind: { prefix: {} }, pattern: "A".repeat(11) },
+      ]);
+    });
+
+    it("Update special symbol rules - too many rules - fail", async () => {
+      await expectInvalidRules(
+        Array.from({ length: 9 }, (_, index) => ({
+          kind: { exact: {} },
+          pattern: `SYM${index}`,
+        }))
+      );
+    });
+
+    it("Update special symbol rules - prefix rule - success", async () => {
+      await program.methods
+        .updateSpecialSymbolRules(rules)
+        .accounts({ platformConfig, admin: platformWallet })
+        .signers([wallet.payer])
+        .rpc();
+      const platformConfigAccount = await program.account.platformConfig.fetch(
+        platformConfig
+      );
+      assert.equal(
+        platformConfigAccount.specialSymbolRules.length,
+        3,
+        "specialSymbolRules is wrong"
+      );
+    });
+
+    it("Initialize Presale - suffix symbol - success", async () => {
+      await prepareCreator(symbolCreatorKeypair);
+      await expectPlatformFee(0, "HELLOSAFE", true);
+    });
+
+    it("Initialize Presale - exact symbol in lowercase - success", async () => {
+      await expectPlatformFee(1, "safu", true);
+    });
+
+    it("Initialize Presale - mixed case prefix symbol - success", async () => {
+      await expectPlatformFee(2, "MoOnCat", true);
+    });
+
+    it("Initialize Presale - near exact symbol - success", async () => {
+      // "SAFUX" only contains the exact "SAFU" pattern, so it is normal
+      await expectPlatformFee(3, "SAFUX", false);
+    });
+  });
+
+  describe("Refund", () => {
+    // userC's 10k stay below the 100k soft cap; without a fee the refund
+    // gives back everything userC paid
+    const refundCreatorKeypair = new Keypair();
+    let presale: PresaleAccounts;
+    let endTime: number;
+    let userCBalanceBefore: bigint;
+
+    const getUserCBalance = async () =>
+      (await getAccount(connection, userCBaseTokenAccount, "processed")).amount;
+
+    it("Refund - userC buy below the soft cap - success", async () => {
+      await prepareCreator(refundCreatorKeypair);
+      const startTime = Math.floor(Date.now() / 1000);
+      endTime = startTime + 6;
+      presale = await createPresale(refundCreatorKeypair, 0, {
+        startTimestamp: new anchor.BN(startTime),
+        duration: new anchor.BN(6),
+        roundEndTimes: [new anchor.BN(endTime)],
+        feePercent: 0,
+      });
+
+      userCBalanceBefore = await getUserCBalance();
+      await buyScenario(
+        presale,
+        userCKeypair,
+        userCBaseTokenAccount,
+        new anchor.BN(10_000).mul(PRECISION)
+      );
+      assert.isTrue(
+        (await getUserCBalance()) < userCBalanceBefore,
+        "userC did not pay"
+      );
+    });
+
+    it("Refund - finalize below the soft cap - success", async () => {
+      while (Date.now() / 1000 < endTime + 1) {
+        await sleep(1);
+      }
+      await finalizeScenario(presale, refundCreatorKeypair);
+      const presaleConfigAccount = await program.account.presaleConfig.fetch(
+        presale.presaleConfig
+      );
+      assert.equal(
+        presaleConfigAccount.isFinalized,
+        true,
+        "isFinalized is wrong"
+      );
+      assert.equal(presaleConfigAccount.isSuccess, false, "isSuccess is wrong");
+    });
+
+    it("Refund - userC refund - success", async () => {
+      await claimScenario(presale, userCKeypair, userCBaseTokenAccount);
+
+      assert.equal(
+        (await getUserCBalance()).toString(),
+        userCBalanceBefore.toString(),
+        "userC balance is not restored"
+      );
+      const userCAllocation = await program.account.userAllocation.fetch(
+        getScenarioAllocation(presale, userC)
+      );
+      assert.equal(
+        userCAllocation.baseAmount.toString(),
+        "0",
+        "baseAmount is wrong"
+      );
+    });
+  });
+
+  describe("Failed Presale Close", () => {
+    // userB pays with the payment mint and userC through userA's referral,
+    // neither reaches the soft cap, so both are refunded less the fee
+    const failedCreatorKeypair = new Keypair();
+    const failedCreator = failedCreatorKeypair.publicKey;
+    const tokens = (amount: number) => new anchor.BN(amount).mul(PRECISION);
+    let presale: PresaleAccounts;
+    let startTime: number;
+    let referrerStats: PublicKey;
+    let vaultPaymentTokenAccount: PublicKey;
+    let creatorBaseTokenAccount: PublicKey;
+    let creatorPaymentTokenAccount: PublicKey;
+
+    const waitUntil = async (timestamp: number) => {
+      while (Date.now() / 1000 < timestamp + 1) {
+        await sleep(1);
+      }
+    };
+    const closeReferrerStats = () =>
+      program.methods
+        .closeReferrerStats()
+        .accounts({
+          presaleConfig: presale.presaleConfig,
+          referrer: userA,
+          referrerStats,
+        })
+        .signers([userAKeypair])
+        .rpc();
+
+    it("Failed Close - creator add payment mint - success", async () => {
+      await prepareCreator(failedCreatorKeypair);
+      startTime = Math.floor(Date.now() / 1000) + 4;
+      presale = await createPresale(failedCreatorKeypair, 0, {
+        startTimestamp: new anchor.BN(startTime),
+        duration: new anchor.BN(8),
+        roundEndTimes: [new anchor.BN(startTime + 8)],
+        referral: configData.referral,
+      });
+      vaultPaymentTokenAccount = pda(
+        [
+          VAULT_BASE_TOKEN_ACCOUNT_TAG,
+          presale.presaleConfig.toBuffer(),
+          paymentMint.toBuffer(),
+        ],
+        programId
+      );
+      referrerStats = pda(
+        [
+          REFERRER_STATS_TAG,
+          presale.presaleConfig.toBuffer(),
+          userA.toBuffer(),
+        ],
+        programId
+      );
+      creatorBaseTokenAccount = (
+        await getOrCreateAssociatedTokenAccount(
+          connection,
+          wallet.payer,
+          baseMint,
+          failedCreator
+        )
+      ).address;
+      creatorPaymentTokenAccount = (
+        await getOrCreateAssociatedTokenAccount(
+          connection,
+          wallet.payer,
+          paymentMint,
+          failedCreator
+        )
+      ).address;
+
+      await program.methods
+        .addPaymentMint(new anchor.BN(1_000_000))
+        .accounts({
+          presaleConfig: presale.presaleConfig,
+          creator: failedCreator,
+          paymentMint,
+          vaultPaymentTokenAccount,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([failedCreatorKeypair])
+        .rpc();
+      await program.methods
+        .registerReferrer()
+        .accounts({
+          presaleConfig: presale.presaleConfig,
+          referrer: userA,
+          referrerStats,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([userAKeypair])
+        .rpc();
+    });
+
+    it("Failed Close - userB and userC buy - success", async () => {
+      await waitUntil(startTime);
+      await program.methods
+        .buyPretoken(0, tokens(10_000), null)
+        .accounts({
+          platformConfig,
+          presaleConfig: presale.presaleConfig,
+          user: userB,
+          userAllocation: getScenarioAllocation(presale, userB),
+          baseMint: paymentMint,
+          vaultBaseTokenAccount: vaultPaymentTokenAccount,
+          userBaseTokenAccount: getAssociatedTokenAddressSync(
+            paymentMint,
+            userB
+          ),
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([userBKeypair])
+        .rpc();
+      await buyScenario(
+        presale,
+        userCKeypair,
+        userCBaseTokenAccount,
+        tokens(10_000),
+        referrerStats
+      );
+    });
+
+    it("Close - userA close referrer stats early - fail", async () => {
+      try {
+        await closeReferrerStats();
+      } catch (err) {
+        assert.equal(err.error.errorMessage, "Presale not finalized");
+      }
+      assert.isNotNull(
+        await connection.getAccountInfo(referrerStats),
+        "referrer stats is closed"
+      );
+    });
+
+    it("Failed Close - finalize below the soft cap - success", async () => {
+      await waitUntil(startTime + 8);
+      await finalizeScenario(presale, failedCreatorKeypair);
+      const presaleConfigAccount = await program.account.presaleConfig.fetch(
+        presale.presaleConfig
+      );
+      assert.equal(presaleConfigAccount.isSuccess, false, "isSuccess is wrong");
+    });
+
+    it("Failed Close - userB and userC refund - success", async () => {
+      await program.methods
+        .claimOrRefund()
+        .accounts({
+          presaleConfig: presale.presaleConfig,
+          user: userB,
+          userAllocation: getScenarioAllocation(presale, userB),
+          newMint: presale.newMint,
+          userTokenAccount: getScenarioTokenAccount(presale, userB),
+          baseMint: paymentMint,
+          vaultBaseTokenAccount: vaultPaymentTokenAccount,
+          userBaseTokenAccount: getAssociatedTokenAddressSync(
+            paymentMint,
+            userB
+          ),
+          tokenProgram: TOKEN_PROGRAM_ID,
+          tokenProgram2022: TOKEN_2022_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([userBKeypair])
+        .rpc();
+      await claimScenario(presale, userCKeypair, userCBaseTokenAccount);
+
+      for (const userKeypair of [userBKeypair, userCKeypair]) {
+        const user = userKeypair.publicKey;
+        await program.methods
+          .closeUserAllocation()
+          .accounts({
+            presaleConfig: presale.presaleConfig,
+            user,
+            userAllocation: getScenarioAllocation(presale, user),
+            userTokenAccount: getScenarioTokenAccount(presale, user),
+            tokenProgram2022: TOKEN_2022_PROGRAM_ID,
+          })
+          .signers([userKeypair])
+          .rpc();
+      }
+    });
+
+    it("Close - userA close referrer stats - success", async () => {
+      const txSig = await closeReferrerStats();
+      console.log(`Transaction Signature: ${txSig}`);
+
+      assert.isNull(
+        await connection.getAccountInfo(referrerStats),
+        "referrer stats is not closed"
+      );
+      const presaleConfigAccount = await program.account.presaleConfig.fetch(
+        presale.presaleConfig
+      );
+      assert.equal(
+        presaleConfigAccount.referrerCount.toString(),
+        "0",
+        "referrerCount is wrong"
+      );
+    });
+
+    it("Close - creator close failed presale - success", async () => {
+      // a stray unit sent to the vault must not keep it open
+      await mintTo(
+        connection,
+        wallet.payer,
+        paymentMint,
+        vaultPaymentTokenAccount,
+        wallet.publicKey,
+        1
+      );
+      const feeAmount = (
+        await getAccount(connection, vaultPaymentTokenAccount, "processed")
+      ).amount;
+      const baseFeeAmount = (
+        await getAccount(connection, presale.vaultBaseTokenAccount, "processed")
+      ).amount;
+      assert.isTrue(feeAmount > BigInt(1), "payment refund fee is missing");
+
+      const txSig = await program.methods
+        .closePresale()
+        .accounts({
+          presaleConfig: presale.presaleConfig,
+          creator: failedCreator,
+          baseMint,
+          vaultBaseTokenAccount: presale.vaultBaseTokenAccount,
+          creatorBaseTokenAccount,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        })
+        .remainingAccounts([
+          { pubkey: paymentMint, isWritable: false, isSigner: false },
+          {
+            pubkey: vaultPaymentTokenAccount,
+            isWritable: true,
+            isSigner: false,
+          },
+          {
+            pubkey: creatorPaymentTokenAccount,
+            isWritable: true,
+            isSigner: false,
+          },
+        ])
+        .signers([failedCreatorKeypair])
+        .rpc();
+      console.log(`Transaction Signature: ${txSig}`);
+
+      // the refund fees are swept to the creator before the vaults close
+      assert.isNull(
+        await connection.getAccountInfo(presale.presaleConfig),
+        "presale config is not closed"
+      );
+      assert.isNull(
+        await connection.getAccountInfo(vaultPaymentTokenAccount),
+        "payment vault is not closed"
+      );
+      const creatorPaymentAccount = await getAccount(
+        connection,
+        creatorPaymentTokenAccount,
+        "processed"
+      );
+      assert.equal(
+        creatorPaymentAccount.amount.toString(),
+        feeAmount.toString(),
+        "creator payment balance is wrong"
+      );
+      const creatorBaseAccount = await getAccount(
+        connection,
+        creatorBaseTokenAccount,
+        "processed"
+      );
+      assert.equal(
+        creatorBaseAccount.amount.toString(),
+        baseFeeAmount.toString(),
+        "creator base balance is wrong"
+      );
     });
   });
 });
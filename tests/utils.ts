@@ -1,4 +1,8 @@
 import * as anchor from "@coral-xyz/anchor";
+import { execFileSync } from "child_process";
+import * as fs from "fs";
+import * as os from "os";
+import * as path from "path";
 
 export function pda(
   seeds: (Buffer | Uint8Array)[],
@@ -11,6 +15,11 @@ export function pda(
   return pdaKey;
 }
 
+// A creator's presales are told apart by their id in the PDA seeds
+export function presaleIdSeed(presaleId: number) {
+  return new anchor.BN(presaleId).toArrayLike(Buffer, "le", 8);
+}
+
 export async function safeAirdrop(
   connection: anchor.web3.Connection,
   destination: anchor.web3.PublicKey,
@@ -26,3 +35,59 @@ export async function safeAirdrop(
     } catch {}
   }
 }
+
+// Decodes the events emitted through `emit_cpi!` in a confirmed transaction
+export async function getCpiEvents(
+  program: anchor.Program<any>,
+  txSig: string
+) {
+  const tx = await program.provider.connection.getTransaction(txSig, {
+    commitment: "confirmed",
+    maxSupportedTransactionVersion: 0,
+  });
+  const events = [];
+  for (const inner of tx?.meta?.innerInstructions ?? []) {
+    for (const ix of inner.instructions) {
+      const data = anchor.utils.bytes.bs58.decode(ix.data);
+      // skip the 8-byte event instruction tag
+      const event = program.coder.events.decode(
+        anchor.utils.bytes.base64.encode(Buffer.from(data.subarray(8)))
+      );
+      if (event) events.push(event);
+    }
+  }
+  return events;
+}
+
+// Builds a round allowlist with the same merkle code the program verifies
+// against, via the `merkle-allowlist` tool in this workspace
+export function buildAllowlist(
+  wallets: { wallet: anchor.web3.PublicKey; cap?: anchor.BN }[]
+) {
+  const csvPath = path.join(os.tmpdir(), `allowlist-${Date.now()}.csv`);
+  fs.writeFileSync(
+    csvPath,
+    [
+      "wallet,cap",
+      ...wallets.map(
+        ({ wallet, cap }) =>
+          `${wallet.toBase58()},${(cap ?? new anchor.BN(0)).toString()}`
+      ),
+    ].join("\n")
+  );
+  const output = execFileSync(
+    "cargo",
+    ["run", "-q", "-p", "merkle-allowlist", "--", csvPath],
+    { encoding: "utf8" }
+  );
+  const { root, proofs } = JSON.parse(output);
+  const toBytes = (hex: string) => Array.from(Buffer.from(hex, "hex"));
+
+  return {
+    root: toBytes(root),
+    getProof: (wallet: anchor.web3.PublicKey) => {
+      const { cap, proof } = proofs[wallet.toBase58()];
+      return { cap: new anchor.BN(cap), proof: proof.map(toBytes) };
+    },
+  };
+}
@@ -0,0 +1,91 @@
+//! Usage: `merkle-allowlist <wallets.csv>`
+//!
+//! Each CSV line is `wallet[,cap]` (an optional `wallet,cap` header is skipped);
+//! a missing or zero cap means the wallet is uncapped. Prints the root and one
+//! proof per wallet as JSON, ready to pass to `initialize_presale` and
+//! `buy_pretoken`.
+
+use std::{env, fs, process, str::FromStr};
+
+use anchor_lang::prelude::Pubkey;
+use presale_token::merkle::{leaf_hash, MerkleTree};
+
+fn to_hex(bytes: &[u8; 32]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn parse_wallets(csv: &str) -> Result<Vec<(Pubkey, u64)>, String> {
+    let mut wallets = Vec::new();
+    for (index, line) in csv.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || (index == 0 && line.starts_with("wallet")) {
+            continue;
+        }
+
+        let mut fields = line.split(',').map(str::trim);
+        let wallet = fields.next().unwrap_or_default();
+        let wallet = Pubkey::from_str(wallet)
+            .map_err(|_| format!("line {}: invalid wallet `{}`", index + 1, wallet))?;
+        let cap = match fields.next() {
+            Some(cap) if !cap.is_empty() => cap
+                .parse::<u64>()
+                .map_err(|_| format!("line {}: invalid cap `{}`", index + 1, cap))?,
+            _ => 0,
+        };
+        wallets.push((wallet, cap));
+    }
+
+    if wallets.is_empty() {
+        return Err("no wallets found".to_string());
+    }
+    Ok(wallets)
+}
+
+fn run() -> Result<String, String> {
+    let path = env::args()
+        .nth(1)
+        .ok_or("usage: merkle-allowlist <wallets.csv>")?;
+    let csv = fs::read_to_string(&path).map_err(|err| format!("{}: {}", path, err))?;
+    let wallets = parse_wallets(&csv)?;
+
+    let tree = MerkleTree::new(
+        wallets
+            .iter()
+            .map(|(wallet, cap)| leaf_hash(wallet, *cap))
+            .collect(),
+    );
+
+    let proofs: Vec<String> = wallets
+        .iter()
+        .enumerate()
+        .map(|(index, (wallet, cap))| {
+            let proof: Vec<String> = tree
+                .proof(index)
+                .iter()
+                .map(|node| format!("\"{}\"", to_hex(node)))
+                .collect();
+            format!(
+                "    \"{}\": {{ \"cap\": \"{}\", \"proof\": [{}] }}",
+                wallet,
+                cap,
+                proof.join(", ")
+            )
+        })
+        .collect();
+
+    Ok(format!(
+        "{{\n  \"root\": \"{}\",\n  \"proofs\": {{\n{}\n  }}\n}}",
+        to_hex(&tree.root()),
+        proofs.join(",\n")
+    ))
+}
+
+fn main() {
+    match run() {
+        Ok(output) => println!("{}", output),
+        Err(err) => {
+            eprintln!("error: {}", err);
+            process::exit(1);
+        }
+    }
+}